@@ -0,0 +1,46 @@
+#![allow(clippy::needless_return)]
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use snek::game::{GameState, SnakeControl};
+
+const SIZE: usize = 200;
+// a quarter of the board
+const LENGTH: usize = SIZE*SIZE/4;
+
+// A big board with a long snake winding back and forth along the bottom
+// rows, its head at the top of them heading up into the empty space above
+fn long_snake() -> GameState {
+    let mut cells = Vec::with_capacity(LENGTH);
+    for y in 0..LENGTH/SIZE {
+        for x in 0..SIZE {
+            cells.push(if y%2 == 0 { (x, y) } else { (SIZE-1-x, y) });
+        }
+    }
+    let mut save = format!("snek-save 1\nsize {} {}\nsnake up", SIZE, SIZE);
+    for (x, y) in cells.iter().rev() {
+        save.push_str(&format!(" {} {}", x, y));
+    }
+    save.push('\n');
+    let mut gs = GameState::load(save.as_bytes()).unwrap();
+    gs.set_seed(1);
+    return gs;
+}
+
+fn engine(c: &mut Criterion) {
+    c.bench_function("100 ticks of a 10000 long snake on 200x200", |b| b.iter_batched(long_snake, |mut gs| {
+        for _ in 0..100 {
+            gs.update(SnakeControl::None, false);
+        }
+        gs
+    }, BatchSize::LargeInput));
+
+    c.bench_function("placing food beside a 10000 long snake on 200x200", |b| b.iter_batched(long_snake, |mut gs| {
+        for _ in 0..400 {
+            gs.gen_food();
+        }
+        gs
+    }, BatchSize::LargeInput));
+}
+
+criterion_group!(benches, engine);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+#![allow(clippy::needless_return)]
+use criterion::{criterion_group, criterion_main, Criterion};
+use tui::backend::TestBackend;
+
+use snek::game::{GameState, RenderData, SnakeControl};
+use snek::ui::{UI, Theme, RenderMode};
+
+const SIZE: usize = 200;
+
+// A game on a big board with plenty of food about, and a UI over a terminal
+// large enough to show all of it
+fn setup(mode: RenderMode) -> (GameState, UI<TestBackend>) {
+    let mut gs = GameState::new([SIZE,SIZE],[SIZE/2,SIZE/2]);
+    gs.set_seed(1);
+    for _ in 0..400 {
+        gs.gen_food();
+    }
+    let backend = TestBackend::new(2*SIZE as u16+2, SIZE as u16+3);
+    let ui = UI::with_backend(backend, Theme::default(), mode).unwrap();
+    return (gs, ui);
+}
+
+// Play a tick and draw it, the way the game loop does
+fn bench_ticks(c: &mut Criterion, name: &str, mode: RenderMode, render: fn(&mut GameState) -> RenderData) {
+    let (mut gs, mut ui) = setup(mode);
+    ui.render(gs.get_render_changes());
+    c.bench_function(name, |b| b.iter(|| {
+        gs.update(SnakeControl::None, false);
+        ui.render(render(&mut gs));
+    }));
+}
+
+fn render(c: &mut Criterion) {
+    for (mode, name) in [(RenderMode::Ascii, "ascii"), (RenderMode::Unicode, "unicode")].iter() {
+        bench_ticks(c, &format!("{} 200x200 whole board", name), *mode, |gs| gs.get_render_map());
+        bench_ticks(c, &format!("{} 200x200 changed cells", name), *mode, GameState::get_render_changes);
+    }
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
@@ -0,0 +1,108 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Simple `key = value` config file, one entry per line. Lines starting with
+// '#' are comments.
+#[derive(Default, Debug, PartialEq)]
+pub struct Config {
+    entries: Vec<(String, String)>,
+    // the file as it was read, so it can be written back with its comments
+    lines: Vec<String>,
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+            return Some(PathBuf::from(dir).join("snek").join("config"));
+        }
+        if let Ok(home) = std::env::var("HOME") {
+            return Some(PathBuf::from(home).join(".config").join("snek").join("config"));
+        }
+        return None;
+    }
+
+    // a missing file is not an error, it just gives an empty config
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let entries = text.lines().filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (key, value) = line.split_at(line.find('=')?);
+            let value = value.trim_start_matches('=');
+            return Some((key.trim().to_string(), value.trim().to_string()));
+        }).collect();
+        return Self { entries, lines: text.lines().map(String::from).collect() };
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        // later entries override earlier ones
+        return self.entries.iter().rev().find(|(k,_)| k == key).map(|(_,v)| v.as_str());
+    }
+
+    // Change the line setting `key`, or add one at the end if there isn't one
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.entries.push((key.to_string(), value.to_string()));
+        let line = format!("{} = {}", key, value);
+        let existing = self.lines.iter().rposition(|l| {
+            let l = l.trim();
+            !l.starts_with('#') && l.find('=').is_some_and(|i| l[..i].trim() == key)
+        });
+        match existing {
+            Some(index) => self.lines[index] = line,
+            None        => self.lines.push(line),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        return fs::write(path, text);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn parse_config() {
+        let config = Config::parse("# comment\ntheme = monochrome\n\nbad line\ndims=5,7\ntheme=default\n");
+        assert_eq!(config.get("theme"), Some("default"));
+        assert_eq!(config.get("dims"), Some("5,7"));
+        assert_eq!(config.get("bad line"), None);
+        assert_eq!(config.get("missing"), None);
+    }
+
+    #[test]
+    fn set_and_save() {
+        let mut config = Config::parse("# my keys\nkeys = vim\ntheme = monochrome\n");
+        config.set("keys", "wasd");
+        config.set("key.up", "k");
+        assert_eq!(config.get("keys"), Some("wasd"));
+        assert_eq!(config.get("key.up"), Some("k"));
+
+        let path = std::env::temp_dir().join(format!("snek-config-test-{}", std::process::id())).join("config");
+        config.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# my keys\nkeys = wasd\ntheme = monochrome\nkey.up = k\n");
+        assert_eq!(Config::load(&path).unwrap().get("keys"), Some("wasd"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn load_missing_config() {
+        let config = Config::load("/this/path/does/not/exist").unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::game::{SpawnRules, SpawnStrategy};
+use crate::scores::GameRecord;
+
+// A day in the (UTC) calendar
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    // from the number of days since 1970-01-01, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;
+        let doy = doe - (365*yoe + yoe/4 - yoe/100);
+        let mp = (5*doy + 2) / 153;
+        let day = (doy - (153*mp + 2)/5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era*400 + if month <= 2 { 1 } else { 0 };
+        return Self { year, month, day };
+    }
+
+    // the same seed for everyone playing on the same day
+    pub fn seed(&self) -> u64 {
+        return (self.year as u64)*10000 + (self.month as u64)*100 + self.day as u64;
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+pub fn today() -> Date {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    return Date::from_days((secs/86400) as i64);
+}
+
+// who to put on the leaderboard
+pub fn player() -> String {
+    let name = std::env::var("USER").unwrap_or_default();
+    let name: String = name.split_whitespace().collect::<Vec<_>>().join("_");
+    if name.is_empty() {
+        return "anonymous".to_string();
+    }
+    return name;
+}
+
+// Everything about a day's game that comes from the date rather than the
+// player's settings
+#[derive(Clone, PartialEq, Debug)]
+pub struct Challenge {
+    pub date: Date,
+    pub seed: u64,
+    pub dims: [usize; 2],
+    pub mode: &'static str,
+    pub spawn: SpawnRules,
+    pub portals: usize,
+    pub critters: usize,
+}
+
+const MODES: [&str; 3] = ["classic", "survival", "time-attack:240"];
+const STRATEGIES: [SpawnStrategy; 4] = [
+    SpawnStrategy::Uniform,
+    SpawnStrategy::AwayFromHead(4),
+    SpawnStrategy::Clustered(2),
+    SpawnStrategy::EdgeBiased,
+];
+
+impl Challenge {
+    pub fn for_date(date: Date) -> Self {
+        let seed = date.seed();
+        let mut rng = StdRng::seed_from_u64(seed);
+        return Self {
+            date,
+            seed,
+            dims: [16, 12],
+            mode: MODES[rng.gen_range(0..MODES.len())],
+            spawn: SpawnRules {
+                strategy: STRATEGIES[rng.gen_range(0..STRATEGIES.len())],
+                max_food: Some(rng.gen_range(3..=6)),
+            },
+            portals: rng.gen_range(0..=2),
+            critters: rng.gen_range(0..=3),
+        };
+    }
+}
+
+// the day's games, best first
+pub fn leaderboard(records: &[GameRecord], date: Date) -> Vec<&GameRecord> {
+    let date = date.to_string();
+    let mut board: Vec<&GameRecord> = records.iter().filter(|r| r.daily.as_ref().is_some_and(|d| d.date == date)).collect();
+    board.sort_by_key(|r| std::cmp::Reverse(r.score));
+    return board;
+}
+
+// whether `player` already has a go at the day's challenge on record
+pub fn has_played(records: &[GameRecord], date: Date, player: &str) -> bool {
+    return leaderboard(records, date).iter().any(|r| r.daily.as_ref().is_some_and(|d| d.player == player));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scores::Daily;
+
+    #[test]
+    fn dates_from_days() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(20745).to_string(), "2026-10-19");
+        assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+    }
+
+    #[test]
+    fn same_day_same_challenge() {
+        let day = Date::from_days(20745);
+        assert_eq!(Challenge::for_date(day), Challenge::for_date(day));
+        assert_eq!(day.seed(), 20261019);
+        assert_ne!(Challenge::for_date(day).seed, Challenge::for_date(Date::from_days(20746)).seed);
+    }
+
+    #[test]
+    fn daily_leaderboard() {
+        let day = Date::from_days(20745);
+        let entry = |score: usize, date: &str, player: &str| GameRecord {
+            score, length: 1, won: false, perfect: false,
+            daily: Some(Daily { date: date.to_string(), player: player.to_string() }),
+        };
+        let records = vec![
+            entry(3, "2026-10-19", "ann"),
+            entry(9, "2026-10-18", "bob"),
+            GameRecord { score: 50, length: 1, won: false, perfect: false, daily: None },
+            entry(7, "2026-10-19", "cat"),
+        ];
+        let board: Vec<usize> = leaderboard(&records, day).iter().map(|r| r.score).collect();
+        assert_eq!(board, vec![7, 3]);
+        assert!(has_played(&records, day, "ann"));
+        assert!(!has_played(&records, day, "bob"));
+    }
+}
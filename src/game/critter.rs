@@ -0,0 +1,254 @@
+use std::fmt;
+use std::str::FromStr;
+use rand::Rng;
+
+use super::util::{Direction, Position};
+use super::topology::Topology;
+
+// Things other than the snake that move about the board. Critters get
+// about the same way the snake does, over the edges of the board wherever
+// the topology joins them up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CritterKind {
+    // moves diagonally, bouncing off edges and walls; deadly
+    Bouncer,
+    // runs away from the snake; worth extra score when caught
+    Mouse,
+    // walks back and forth along a line; deadly
+    Patrol,
+}
+
+impl CritterKind {
+    pub const ALL: [CritterKind; 3] = [CritterKind::Mouse, CritterKind::Bouncer, CritterKind::Patrol];
+
+    pub fn is_hazard(&self) -> bool {
+        return *self != CritterKind::Mouse;
+    }
+}
+
+impl fmt::Display for CritterKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CritterKind::Bouncer => "bouncer",
+            CritterKind::Mouse   => "mouse",
+            CritterKind::Patrol  => "patrol",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for CritterKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CritterKind::ALL.iter().find(|k| k.to_string() == s).copied().ok_or_else(|| format!("unknown critter '{}'", s))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Critter {
+    pub kind: CritterKind,
+    pub pos: Position,
+    // the way it goes each tick; mice pay it no mind
+    pub heading: Direction,
+}
+
+// One step from `pos` over the board and the way it's heading after, or
+// None if that runs into a wall or off the edge. A diagonal the grid doesn't
+// have is taken as a step up or down and then one to the side.
+fn step(topology: &Topology, pos: Position, dir: Direction) -> Option<(Position, Direction)> {
+    let parts = match dir.split() {
+        Some((vertical, horizontal)) if topology.grid().offset(dir).is_none() => vec![vertical, horizontal],
+        _ => vec![dir],
+    };
+    let (mut pos, mut heading) = (pos, dir);
+    for part in parts {
+        // over a twisted edge, up becomes down
+        if topology.flips(pos, part) {
+            heading = heading.flip_vertical();
+        }
+        pos = topology.step(pos, part)?;
+    }
+    return Some((pos, heading));
+}
+
+impl Critter {
+    pub fn new<T: Into<Position>>(kind: CritterKind, pos: T, heading: Direction) -> Self {
+        Self { kind, pos: pos.into(), heading }
+    }
+
+    // Move one tick. `blocked` says which cells the critter may not enter,
+    // and `from_head` how far a cell is from the snake's head.
+    pub fn advance<R: Rng>(&mut self, topology: &Topology, from_head: &dyn Fn(Position) -> usize, blocked: &dyn Fn(Position) -> bool, rng: &mut R) {
+        let free = |to: Option<(Position, Direction)>| to.filter(|(p, _)| !blocked(*p));
+        match self.kind {
+            CritterKind::Bouncer => {
+                let mut heading = self.heading;
+                // glance off an edge or wall, turning back from a corner
+                if step(topology, self.pos, heading).is_none() {
+                    let bounces = [heading.flip_horizontal(), heading.flip_vertical(), heading.opposite()];
+                    heading = bounces.iter().copied()
+                        .find(|dir| step(topology, self.pos, *dir).is_some())
+                        .unwrap_or(heading);
+                }
+                // bounce straight back off anything in the way
+                if free(step(topology, self.pos, heading)).is_none() {
+                    heading = heading.opposite();
+                }
+                self.heading = heading;
+                if let Some((next, heading)) = free(step(topology, self.pos, heading)) {
+                    self.pos = next;
+                    self.heading = heading;
+                }
+            },
+            CritterKind::Patrol => {
+                if free(step(topology, self.pos, self.heading)).is_none() {
+                    self.heading = self.heading.opposite();
+                }
+                if let Some((next, heading)) = free(step(topology, self.pos, self.heading)) {
+                    self.pos = next;
+                    self.heading = heading;
+                }
+            },
+            CritterKind::Mouse => {
+                // mice only manage to move half the time, so they can be caught
+                if rng.gen_bool(0.5) {
+                    return;
+                }
+                let mut options = vec![self.pos];
+                for dir in topology.grid().directions().iter() {
+                    if let Some((next, _)) = free(step(topology, self.pos, *dir)) {
+                        options.push(next);
+                    }
+                }
+                let best = options.iter().map(|p| from_head(*p)).max().unwrap();
+                options.retain(|p| from_head(*p) == best);
+                self.pos = options[rng.gen_range(0..options.len())];
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use super::super::topology::Wrap;
+    use super::super::grid;
+
+    // a 5x5 board with walls all round
+    fn boxed() -> Topology {
+        return Topology::new(Wrap::Rectangle, [5,5].into());
+    }
+
+    fn anywhere(_: Position) -> usize {
+        return 0;
+    }
+
+    #[test]
+    fn bouncer_bounces_off_edges() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let t = boxed();
+        let mut c = Critter::new(CritterKind::Bouncer, [3,3], Direction::UpRight);
+        let nothing = |_: Position| false;
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!(c.pos, [4,4].into());
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!(c.pos, [3,3].into());
+        assert_eq!(c.heading, Direction::DownLeft);
+
+        // off a wall on one side only
+        let mut c = Critter::new(CritterKind::Bouncer, [4,2], Direction::UpRight);
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!(c.pos, [3,3].into());
+        assert_eq!(c.heading, Direction::UpLeft);
+
+        // where the board wraps there's no edge to bounce off
+        let t = Topology::new(Wrap::Torus, [5,5].into());
+        let mut c = Critter::new(CritterKind::Bouncer, [4,2], Direction::UpRight);
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!(c.pos, [0,3].into());
+        assert_eq!(c.heading, Direction::UpRight);
+    }
+
+    #[test]
+    fn bouncer_bounces_off_obstacles() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut c = Critter::new(CritterKind::Bouncer, [2,2], Direction::UpRight);
+        let wall = |p: Position| p == [3,3].into();
+        c.advance(&boxed(), &anywhere, &wall, &mut rng);
+        assert_eq!(c.pos, [1,1].into());
+    }
+
+    #[test]
+    fn patrol_turns_round() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut c = Critter::new(CritterKind::Patrol, [3,1], Direction::Right);
+        let wall = |p: Position| p == [1,1].into();
+        let mut path = Vec::new();
+        for _ in 0..6 {
+            c.advance(&boxed(), &anywhere, &wall, &mut rng);
+            path.push(c.pos.x);
+        }
+        assert_eq!(path, vec![4,3,2,3,4,3]);
+    }
+
+    #[test]
+    fn critters_follow_the_grid() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut t = boxed();
+        t.set_grid(&grid::HEX);
+        let nothing = |_: Position| false;
+        let mut c = Critter::new(CritterKind::Patrol, [2,2], Direction::UpLeft);
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!(c.pos, [1,3].into());
+        // walls stop critters as they do the snake
+        t.add_wall([0,4].into());
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!(c.heading, Direction::DownRight);
+        assert_eq!(c.pos, [2,2].into());
+    }
+
+    #[test]
+    fn bouncers_turn_over_on_twisted_edges() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let t = Topology::new(Wrap::Mobius, [5,5].into());
+        let nothing = |_: Position| false;
+        let mut c = Critter::new(CritterKind::Bouncer, [4,1], Direction::UpRight);
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!((c.pos, c.heading), ([0,2].into(), Direction::DownRight));
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!(c.pos, [1,1].into());
+        // and turned round, it goes back the way it came
+        let mut c = Critter::new(CritterKind::Bouncer, [0,2], Direction::UpLeft);
+        c.advance(&t, &anywhere, &nothing, &mut rng);
+        assert_eq!((c.pos, c.heading), ([4,1].into(), Direction::DownLeft));
+    }
+
+    #[test]
+    fn mouse_runs_away() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let t = boxed();
+        let head: Position = [0,2].into();
+        let distances = t.distances(head);
+        let from_head = |p: Position| distances[p.x][p.y].unwrap_or(0);
+        let mut c = Critter::new(CritterKind::Mouse, [1,2], Direction::Up);
+        let snake = |p: Position| p == head;
+        for _ in 0..20 {
+            let before = from_head(c.pos);
+            c.advance(&t, &from_head, &snake, &mut rng);
+            assert!(from_head(c.pos) >= before);
+        }
+        // ends up in one of the far corners
+        assert_eq!(c.pos.x, 4);
+        assert!(c.pos.y == 0 || c.pos.y == 4);
+    }
+
+    #[test]
+    fn parse_kinds() {
+        for k in CritterKind::ALL.iter() {
+            assert_eq!(CritterKind::from_str(&k.to_string()), Ok(*k));
+        }
+        assert!(!CritterKind::Mouse.is_hazard());
+        assert!(CritterKind::Patrol.is_hazard());
+    }
+}
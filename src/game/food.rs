@@ -0,0 +1,159 @@
+use std::ops::Index;
+use std::slice::Iter;
+
+use super::occupancy::Occupancy;
+use super::util::{Food, Position, Size};
+
+// The food on the board, along with which food is on each cell, so
+// checking a cell or eating what's on it needs no search. Eating food puts
+// the last one down in its place, so the order isn't kept. There's never
+// more than one food to a cell.
+#[derive(Clone, Debug)]
+pub struct Foods {
+    list: Vec<Food>,
+    at: Occupancy<Option<usize>>,
+}
+
+impl PartialEq for Foods {
+    fn eq(&self, other: &Self) -> bool {
+        return self.list == other.list;
+    }
+}
+
+impl Index<usize> for Foods {
+    type Output = Food;
+    fn index(&self, index: usize) -> &Food {
+        return &self.list[index];
+    }
+}
+
+impl Foods {
+    pub fn new(size: Size) -> Self {
+        return Self { list: Vec::new(), at: Occupancy::new(size) };
+    }
+
+    // Start again on a board of a different size, keeping the food that's
+    // still on it
+    pub fn resize(&mut self, size: Size) {
+        let list = std::mem::take(&mut self.list);
+        self.at = Occupancy::new(size);
+        for food in list {
+            if food.pos.x < size.x && food.pos.y < size.y {
+                self.push(food);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        return self.list.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.list.is_empty();
+    }
+
+    pub fn iter(&self) -> Iter<'_, Food> {
+        return self.list.iter();
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        return self.at.get(pos).is_some();
+    }
+
+    // Put down some food, unless its cell already has some. Returns whether
+    // it went down.
+    pub fn push(&mut self, food: Food) -> bool {
+        if self.contains(food.pos) {
+            return false;
+        }
+        self.at.set(food.pos, Some(self.list.len()));
+        self.list.push(food);
+        return true;
+    }
+
+    // take away the food on `pos`, if there is any, to be eaten
+    pub fn take(&mut self, pos: Position) -> Option<Food> {
+        let index = self.at.get(pos)?;
+        self.at.set(pos, None);
+        let food = self.list.swap_remove(index);
+        // the last food has moved into the gap
+        if let Some(moved) = self.list.get(index) {
+            self.at.set(moved.pos, Some(index));
+        }
+        return Some(food);
+    }
+
+    // Move a food to another cell, unless there's food there already.
+    // Returns whether it moved.
+    pub fn move_to(&mut self, index: usize, pos: Position) -> bool {
+        if self.contains(pos) {
+            return false;
+        }
+        self.at.set(self.list[index].pos, None);
+        self.at.set(pos, Some(index));
+        self.list[index].pos = pos;
+        return true;
+    }
+
+    // Count down the life of every food that goes off, clearing away any
+    // that has run out
+    pub fn rot(&mut self) {
+        let before = self.list.len();
+        let at = &mut self.at;
+        self.list.retain_mut(|food| match food.lifetime {
+            Some(0) => {
+                at.set(food.pos, None);
+                false
+            },
+            Some(remainder) => {
+                food.lifetime = Some(remainder - 1);
+                true
+            },
+            None => true,
+        });
+        if self.list.len() != before {
+            self.reindex();
+        }
+    }
+
+    fn reindex(&mut self) {
+        for (index, food) in self.list.iter().enumerate() {
+            self.at.set(food.pos, Some(index));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::util::FoodGroup;
+
+    #[test]
+    fn find_food_by_cell() {
+        let mut foods = Foods::new(Size { x: 5, y: 5 });
+        assert!(foods.push(Food::new([1,1], FoodGroup::Grow)));
+        assert!(foods.push(Food::new_with_lifetime([2,2], FoodGroup::Shrink, 1)));
+        assert!(foods.push(Food::new([3,3], FoodGroup::Poison)));
+        // one to a cell
+        assert!(!foods.push(Food::new([3,3], FoodGroup::Grow)));
+        assert!(foods.contains([2,2].into()));
+
+        assert_eq!(foods.take([1,1].into()), Some(Food::new([1,1], FoodGroup::Grow)));
+        assert_eq!(foods.take([1,1].into()), None);
+        // the last food filled the gap, and can still be found
+        assert_eq!(foods[0].pos, [3,3].into());
+        assert_eq!(foods.take([3,3].into()).map(|f| f.group), Some(FoodGroup::Poison));
+        assert!(foods.push(Food::new([3,3], FoodGroup::Poison)));
+        assert!(foods.move_to(1, [4,4].into()));
+        assert!(!foods.contains([3,3].into()));
+
+        // the shrink food lasts one more tick
+        foods.rot();
+        assert_eq!(foods.len(), 2);
+        foods.rot();
+        assert_eq!(foods.len(), 1);
+        assert!(!foods.contains([2,2].into()));
+        assert_eq!(foods.take([4,4].into()).map(|f| f.group), Some(FoodGroup::Poison));
+        assert!(foods.is_empty());
+    }
+}
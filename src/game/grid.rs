@@ -0,0 +1,143 @@
+use std::fmt;
+
+use super::util::Direction;
+
+// The shape of the cells, for anything that has to draw them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+    Square,
+    // each row is drawn half a cell to the right of the one below
+    Hex,
+}
+
+// How cells fit together: which ways the snake can head from a cell and
+// where each one leads. Everything that moves does so through the board's
+// grid, so the rules in `GameState` work the same on any of them.
+pub trait Grid: Sync {
+    fn name(&self) -> &'static str;
+
+    fn shape(&self) -> Shape;
+
+    // every way the snake can head, going round clockwise
+    fn directions(&self) -> &'static [Direction];
+
+    // the change in position for one step, None if there's no such way on
+    // this grid
+    fn offset(&self, dir: Direction) -> Option<(isize, isize)>;
+}
+
+impl fmt::Debug for dyn Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl PartialEq for dyn Grid {
+    fn eq(&self, other: &Self) -> bool {
+        return self.name() == other.name();
+    }
+}
+
+// The usual grid of square cells
+pub struct Square;
+
+impl Grid for Square {
+    fn name(&self) -> &'static str {
+        return "square";
+    }
+
+    fn shape(&self) -> Shape {
+        return Shape::Square;
+    }
+
+    fn directions(&self) -> &'static [Direction] {
+        return &[Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+    }
+
+    fn offset(&self, dir: Direction) -> Option<(isize, isize)> {
+        match dir {
+            Direction::Up    => Some((0,1)),
+            Direction::Down  => Some((0,-1)),
+            Direction::Right => Some((1,0)),
+            Direction::Left  => Some((-1,0)),
+            _ => None,
+        }
+    }
+}
+
+// Hexagonal cells in axial coordinates: x runs along a row and y up the
+// board, with each row drawn half a cell to the right of the one below.
+// There's no straight up or down, just the four diagonals.
+pub struct Hex;
+
+impl Grid for Hex {
+    fn name(&self) -> &'static str {
+        return "hex";
+    }
+
+    fn shape(&self) -> Shape {
+        return Shape::Hex;
+    }
+
+    fn directions(&self) -> &'static [Direction] {
+        return &[
+            Direction::UpRight, Direction::Right, Direction::DownRight,
+            Direction::DownLeft, Direction::Left, Direction::UpLeft,
+        ];
+    }
+
+    fn offset(&self, dir: Direction) -> Option<(isize, isize)> {
+        match dir {
+            Direction::UpRight   => Some((0,1)),
+            Direction::Right     => Some((1,0)),
+            Direction::DownRight => Some((1,-1)),
+            Direction::DownLeft  => Some((0,-1)),
+            Direction::Left      => Some((-1,0)),
+            Direction::UpLeft    => Some((-1,1)),
+            _ => None,
+        }
+    }
+}
+
+pub static SQUARE: Square = Square;
+pub static HEX: Hex = Hex;
+
+pub const NAMES: [&str; 2] = ["square", "hex"];
+
+pub fn from_name(name: &str) -> Result<&'static dyn Grid, String> {
+    match name {
+        "square" => Ok(&SQUARE),
+        "hex"    => Ok(&HEX),
+        _ => Err(format!("unknown grid '{}', expected one of: {}", name, NAMES.join(", "))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_grids() {
+        for name in NAMES.iter() {
+            assert_eq!(from_name(name).unwrap().name(), *name);
+        }
+        assert!(from_name("triangle").is_err());
+        assert_eq!(SQUARE.shape(), Shape::Square);
+        assert_eq!(HEX.shape(), Shape::Hex);
+    }
+
+    #[test]
+    fn every_way_has_a_way_back() {
+        for grid in [&SQUARE as &dyn Grid, &HEX].iter() {
+            let mut offsets = Vec::new();
+            for dir in grid.directions().iter() {
+                let (dx, dy) = grid.offset(*dir).unwrap();
+                assert_eq!(grid.offset(dir.opposite()), Some((-dx, -dy)), "{} on {:?}", dir, grid);
+                assert!(!offsets.contains(&(dx, dy)));
+                offsets.push((dx, dy));
+            }
+        }
+        assert_eq!(HEX.offset(Direction::Up), None);
+        assert_eq!(SQUARE.offset(Direction::UpLeft), None);
+    }
+}
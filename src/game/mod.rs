@@ -1,34 +1,164 @@
 mod sprites;
 mod util;
+mod save;
+mod powerup;
+mod portal;
+mod critter;
+mod spawn;
+mod scoring;
+mod topology;
+mod occupancy;
+mod food;
+pub mod grid;
+pub mod mode;
 
-use sprites::{Snake};
-pub use sprites::{SnakeState,Reason};
+use sprites::{Snake, Reason};
+pub use sprites::SPEED_UNIT;
+pub use sprites::{SnakeState, CollisionPolicy};
+pub use powerup::{PowerUp, Effect};
+use portal::Portal;
+use critter::Critter;
+use food::Foods;
+pub use critter::CritterKind;
+pub use spawn::{SpawnRules, SpawnStrategy};
+pub use scoring::{ScoringRules, ScoreBreakdown};
+use scoring::{Scorer, Meal};
+use mode::{GameMode, Classic};
+pub use topology::{Topology, Wrap, LEVELS};
 
 use util::{Size, Direction, Position, Food};
 pub use util::FoodGroup;
 
-use rand::{prelude::ThreadRng,Rng};
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use std::str::FromStr;
+use rand::{rngs::StdRng,Rng,SeedableRng};
 
 
 pub type RenderMap = Vec<Vec<Item>>;
 
 pub struct RenderData {
-    pub rm: RenderMap,
+    // shared with the game, which keeps the board between ticks
+    pub rm: Rc<RenderMap>,
+    // the cells that differ from the last board handed out by
+    // `get_render_changes`, or None when the whole board should be drawn
+    pub changed: Option<Vec<[usize;2]>>,
+    // snake segment positions, head first
+    pub snake: Vec<[usize;2]>,
     pub score: usize,
-    pub log_data: [Option<String>; 4],
+    // meals eaten in quick succession
+    pub combo: usize,
+    // in quarter cells per tick
+    pub speed: usize,
+    // anything the game mode wants to show, like time left
+    pub status: Option<String>,
+    // power-ups acting on the snake
+    pub effects: Vec<Effect>,
+    // the cells are hexagons, with each row drawn half a cell right of the
+    // one below
+    pub hex: bool,
+    // the cell the head moves into next, if it isn't heading into a wall
+    pub planned: Option<[usize;2]>,
 }
 
 pub struct GameState {
     snake: Snake,
-    foods: Vec<util::Food>,
+    // the shape of the board, which the snake is handed when it moves
+    topology: Topology,
+    foods: Foods,
+    critters: Vec<Critter>,
     map_size: Size,
-    score: usize,
-    rng: ThreadRng,
+    scorer: Scorer,
+    ticks: usize,
+    spawn: SpawnRules,
+    // the game is won on reaching this length, as well as on filling the board
+    target_length: Option<usize>,
+    // ate something that shrank the snake, so the game can't be perfect
+    shrunk: bool,
+    mode: Box<dyn GameMode>,
+    rng: StdRng,
+    // a cell the player picked, which the snake steers for until it gets
+    // there or is steered some other way
+    target: Option<Position>,
+    // how far every cell is from the last target, kept until the target or
+    // the board changes
+    route: Option<(Position, Vec<Vec<Option<usize>>>)>,
+    // the board as last handed out for drawing, and every cell with
+    // something other than a wall on it, so the next board only needs the
+    // cells that have changed
+    board: Rc<RenderMap>,
+    drawn: Vec<Position>,
 }
 
-#[derive(PartialEq)]
+// foods this close to the head are pulled in by a magnet
+const MAGNET_RANGE: usize = 3;
+// critters don't appear any closer than this to the head
+const CRITTER_SPAWN_DISTANCE: usize = 3;
+// tiles tried at random for new food before looking for every free one
+const FREE_CELL_GUESSES: usize = 32;
+
+#[derive(PartialEq, Debug)]
 pub enum SnakeControl {
     Up,Down,Left,Right,None,
+    // the diagonals, for the hex grid
+    UpLeft,UpRight,DownLeft,DownRight,
+    // speed up or slow down a notch
+    Faster,Slower,
+    // turn a notch from the way the snake last moved
+    TurnLeft,TurnRight,
+    // head for this cell, steering round anything in the way
+    Goto([usize;2]),
+}
+
+// How the player's keys steer the snake
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ControlScheme {
+    // each key points the snake a fixed way
+    #[default]
+    Absolute,
+    // left and right turn the snake from the way it's going, and nothing
+    // else steers it
+    Relative,
+}
+
+impl ControlScheme {
+    pub const NAMES: [&'static str; 2] = ["absolute", "relative"];
+
+    // what a control means under this scheme
+    pub fn apply(&self, control: SnakeControl) -> SnakeControl {
+        if *self == ControlScheme::Absolute {
+            return control;
+        }
+        match control {
+            SnakeControl::Left  => SnakeControl::TurnLeft,
+            SnakeControl::Right => SnakeControl::TurnRight,
+            SnakeControl::Up | SnakeControl::Down | SnakeControl::UpLeft | SnakeControl::UpRight
+                | SnakeControl::DownLeft | SnakeControl::DownRight => SnakeControl::None,
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ControlScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ControlScheme::Absolute => "absolute",
+            ControlScheme::Relative => "relative",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ControlScheme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absolute" => Ok(ControlScheme::Absolute),
+            "relative" => Ok(ControlScheme::Relative),
+            _ => Err(format!("unknown control scheme '{}', expected one of: {}", s, Self::NAMES.join(", "))),
+        }
+    }
 }
 
 impl From<SnakeControl> for Direction {
@@ -38,7 +168,12 @@ impl From<SnakeControl> for Direction {
             SnakeControl::Down => Direction::Down,
             SnakeControl::Left => Direction::Left,
             SnakeControl::Right => Direction::Right,
-            SnakeControl::None => unreachable!(),
+            SnakeControl::UpLeft => Direction::UpLeft,
+            SnakeControl::UpRight => Direction::UpRight,
+            SnakeControl::DownLeft => Direction::DownLeft,
+            SnakeControl::DownRight => Direction::DownRight,
+            SnakeControl::None | SnakeControl::Faster | SnakeControl::Slower
+                | SnakeControl::TurnLeft | SnakeControl::TurnRight | SnakeControl::Goto(_) => unreachable!(),
         }
     }
 }
@@ -53,134 +188,621 @@ impl GameState {
         let pos = snake_position.into();
         let snake_x = pos.x;
         let snake_y = pos.y;
-        let rng = rand::thread_rng();
+        let rng = StdRng::from_entropy();
         Self {
             snake: Snake::new([snake_x,snake_y], map_size.clone().into()),
-            foods: Vec::new(),
+            topology: Topology::new(Default::default(), map_size.clone().into()),
+            foods: Foods::new(map_size.clone().into()),
+            critters: Vec::new(),
             map_size: map_size.into(),
-            score: 0,
+            scorer: Scorer::default(),
+            ticks: 0,
+            spawn: SpawnRules::default(),
+            target_length: None,
+            shrunk: false,
+            mode: Box::new(Classic),
             rng,
+            target: None,
+            route: None,
+            board: Rc::default(),
+            drawn: Vec::new(),
         }
     }
 
     pub fn update_and_render(&mut self,control: SnakeControl, gen_new_food: bool) -> (SnakeState, RenderData) {
         let ss : SnakeState = self.update(control, gen_new_food);
-        let rm : RenderData  = self.get_render_map();
+        let rm : RenderData  = self.get_render_changes();
         return (ss,rm);
     }
 
-    pub fn update(&mut self,control: SnakeControl, gen_new_food: bool) -> SnakeState {
-        // apply control input to snake
-        if control != SnakeControl::None {
-            self.snake.set_dir(control.into());
+    // Act on a control straight away, rather than waiting for the next tick
+    pub fn steer(&mut self, control: SnakeControl) {
+        match control {
+            SnakeControl::None   => {},
+            SnakeControl::Faster => self.snake.accelerate(),
+            SnakeControl::Slower => self.snake.brake(),
+            SnakeControl::Goto(cell) => self.set_target(cell.into()),
+            // steering by hand forgets about any target
+            SnakeControl::TurnLeft  => {
+                self.target = None;
+                self.snake.turn(false, &self.topology);
+            },
+            SnakeControl::TurnRight => {
+                self.target = None;
+                self.snake.turn(true, &self.topology);
+            },
+            dir => {
+                self.target = None;
+                self.snake.set_dir(dir.into(), &self.topology);
+            },
         }
+        // so the way the snake is about to go shows straight away
+        self.steer_to_target();
+    }
 
-        //// get next head pos
-        let next_head_pos = self.snake.get_next_head_pos();
-        //// check if theres any food
-        let mut food_to_eat: Option<FoodGroup> = None;
-        for index in 0..self.foods.len() {
-            if self.foods[index].pos == next_head_pos {
-                let food = self.foods.remove(index);
-                food_to_eat = Some(food.group);
-                self.score += 1;
+    pub fn update(&mut self,control: SnakeControl, gen_new_food: bool) -> SnakeState {
+        self.steer(control);
 
-                break;
+        // each snake covers ground at its own speed, so may move more or
+        // less than once a tick
+        let steps = self.snake.steps_this_tick();
+        for _ in 0..steps {
+            self.steer_to_target();
+            let snake_state = match self.step_snake() {
+                SnakeState::Dead(reason) if self.mode.forgives(&reason) => self.forgive(reason),
+                state => state,
+            };
+            if snake_state != SnakeState::Alive {
+                return snake_state;
             }
         }
-        // println!("food to eat: {:?}",food_to_eat);
-        //// let the snake slither and give it any food it finds
-        let snake_state = self.snake.slither(food_to_eat);
+        self.ticks += 1;
+        self.scorer.survive(self.mode.tick_points());
+        // going faster than normal costs points
+        if self.snake.speed() > SPEED_UNIT {
+            self.scorer.penalise(self.scorer.rules.boost);
+        }
+        self.snake.effects_mut().tick();
+
+        if self.snake.effects().is_active(PowerUp::Magnet) {
+            self.pull_food();
+        }
+
+        let critter_state = match self.move_critters() {
+            SnakeState::Dead(reason) if self.mode.forgives(&reason) => self.forgive(reason),
+            state => state,
+        };
+        if critter_state != SnakeState::Alive {
+            return critter_state;
+        }
+
         if gen_new_food {
             self.gen_food();
         }
 
         // remove any food abt to rot
-        self.foods = self.foods.iter().filter_map(|f| {
-            if let Some(remainder) = f.lifetime {
-                if remainder != 0 {
-                    let mut new_f = f.clone();
-                    new_f.lifetime = Some(remainder - 1);
-                    return Some(new_f);
-
-                } else {
-                    return None;
+        self.foods.rot();
+
+        if self.has_won() {
+            return SnakeState::Won;
+        }
+        return self.mode.check(self.ticks);
+    }
+
+    // Head for `cell` from now on, unless it's somewhere the snake can't go
+    pub fn set_target(&mut self, cell: Position) {
+        if self.topology.contains(cell) {
+            self.target = Some(cell);
+        }
+    }
+
+    // Point the snake at the neighbouring cell nearest the target, keeping
+    // off its own body where it can. Going straight wins a tie if it gets
+    // closer, and loses one if it doesn't, so a target behind the snake is
+    // reached by turning round rather than carrying on away from it.
+    fn steer_to_target(&mut self) {
+        let target = match self.target {
+            Some(target) => target,
+            None         => return,
+        };
+        let head = self.snake.head_pos();
+        if head == target {
+            self.target = None;
+            return;
+        }
+        let fresh = match &self.route {
+            Some((to, _)) => *to != target,
+            None          => true,
+        };
+        if fresh {
+            self.route = Some((target, self.topology.distances(target)));
+        }
+        let distances = match &self.route {
+            Some((_, distances)) => distances,
+            None                 => return,
+        };
+        let topology = &self.topology;
+        let here = distances[head.x][head.y];
+        let heading = self.snake.get_dir();
+        let best = topology.grid().directions().iter()
+            .filter(|dir| **dir != heading.opposite())
+            .filter_map(|dir| {
+                let next = topology.step(head, *dir)?;
+                let distance = distances[next.x][next.y]?;
+                let straight = match (*dir == heading, Some(distance) < here) {
+                    (true, true)  => 0,
+                    (false, _)    => 1,
+                    (true, false) => 2,
+                };
+                Some(((self.snake.is_in_snake(next), distance, straight), *dir))
+            })
+            .min_by_key(|(rank, _)| *rank);
+        if let Some((_, dir)) = best {
+            self.snake.set_dir(dir, &self.topology);
+        }
+    }
+
+    // move the snake one cell, eating anything in its way
+    fn step_snake(&mut self) -> SnakeState {
+        //// get next head pos
+        let next_head_pos = match self.snake.get_next_head_pos(&self.topology) {
+            Some(pos) => pos,
+            None      => return SnakeState::Dead(Reason::Wall),
+        };
+        //// check for critters
+        if let Some(index) = self.critters.iter().position(|c| c.pos == next_head_pos) {
+            if self.critters[index].kind.is_hazard() {
+                return SnakeState::Dead(Reason::Hazard);
+            }
+            // caught a mouse, which feeds the snake like food
+            self.critters.remove(index);
+            self.score_meal(self.scorer.rules.mouse, None);
+            let snake_state = self.snake.slither(Some(FoodGroup::Grow), &self.topology);
+            self.spawn_critter(CritterKind::Mouse);
+            return snake_state;
+        }
+        //// check if theres any food
+        let mut food_to_eat: Option<FoodGroup> = None;
+        if let Some(food) = self.foods.take(next_head_pos) {
+            if food.group == FoodGroup::Shrink {
+                self.shrunk = true;
+            }
+            food_to_eat = Some(food.group);
+            self.score_meal(self.scorer.rules.value(food.group), food.lifetime);
+        }
+        //// biting the body cuts it off rather than killing, if the rules say so
+        if self.snake.collision() == CollisionPolicy::SelfCut && self.snake.bites_itself(food_to_eat, &self.topology) {
+            let lost = self.snake.trim_from(next_head_pos);
+            self.shrunk = true;
+            self.scorer.penalise(lost*self.scorer.rules.cut);
+        }
+        //// let the snake slither and give it any food it finds
+        return self.snake.slither(food_to_eat, &self.topology);
+    }
+
+    // Carry on after something that would have killed the snake, for modes
+    // where nothing does.
+    fn forgive(&mut self, reason: Reason) -> SnakeState {
+        let next_head_pos = self.snake.get_next_head_pos(&self.topology);
+        match reason {
+            // lose everything from where it bit itself
+            Reason::Collision => {
+                if let Some(pos) = next_head_pos {
+                    self.snake.trim_from(pos);
                 }
-            } else {
-                return Some(*f);
+                return self.snake.slither(None, &self.topology);
+            },
+            // the poison was eaten, but does no harm
+            Reason::Poison => return self.snake.slither(None, &self.topology),
+            // knock the hazard off the board, and carry on unless it ran
+            // into the head after the snake had already moved
+            Reason::Hazard => {
+                let head = self.snake.head_pos();
+                let moved = self.critters.iter().any(|c| c.pos == head);
+                self.critters.retain(|c| Some(c.pos) != next_head_pos && c.pos != head);
+                if moved {
+                    return SnakeState::Alive;
+                }
+                return self.snake.slither(None, &self.topology);
+            },
+            // wait at the wall for the player to turn
+            Reason::Wall => return SnakeState::Alive,
+        }
+    }
+
+    fn score_meal(&mut self, value: isize, lifetime: Option<usize>) {
+        let meal = Meal {
+            value,
+            tick: self.ticks,
+            lifetime,
+            length: self.snake_length(),
+            multiplier: self.snake.effects().score_multiplier(),
+        };
+        self.scorer.eat(meal);
+    }
+
+    // let every critter take its turn, returning Dead if a hazard runs into
+    // the snake's head
+    fn move_critters(&mut self) -> SnakeState {
+        let head = self.snake.head_pos();
+        let GameState { snake, topology, foods, critters, rng, .. } = self;
+        // mice keep as many steps from the head as they can
+        let distances = match critters.iter().any(|c| c.kind == CritterKind::Mouse) {
+            true  => topology.distances(head),
+            false => Vec::new(),
+        };
+        let from_head = |pos: Position| distances.get(pos.x).and_then(|column| column[pos.y]).unwrap_or(usize::MAX);
+        for index in 0..critters.len() {
+            let mut critter = critters[index];
+            let hazard = critter.kind.is_hazard();
+            {
+                let blocked = |pos: Position| {
+                    !topology.contains(pos)
+                        || (snake.is_in_snake(pos) && !(hazard && pos == head))
+                        || topology.is_portal(pos)
+                        || foods.contains(pos)
+                        || critters.iter().any(|c| c.pos == pos)
+                };
+                critter.advance(topology, &from_head, &blocked, rng);
+            }
+            critters[index] = critter;
+            if hazard && critter.pos == head {
+                return SnakeState::Dead(Reason::Hazard);
             }
-        }).collect();
+        }
+        return SnakeState::Alive;
+    }
 
-        return snake_state;
+    // nothing at all on this tile
+    fn is_free(&self, pos: Position) -> bool {
+        return self.is_unclaimed(pos) && !self.critters.iter().any(|c| c.pos == pos);
     }
 
-    pub fn gen_food(&mut self) {
-        if self.rng.gen_range(0..4) ==0 {
-            
-            let mut food_pos: Position;
-            loop {
-                let x = self.rng.gen_range(0..self.map_size.x); 
-                let y = self.rng.gen_range(0..self.map_size.y); 
-                food_pos = [x,y].into();
-                if !self.snake.is_in_snake(food_pos) { break }
-            }
-
-            let food = match self.rng.gen_range(0..10) {
-                0..=5 => Food::new(food_pos, FoodGroup::Grow),
-                6..=7 => Food::new_with_lifetime(food_pos, FoodGroup::Poison, 10),
-                8..=9 => Food::new_with_lifetime(food_pos, FoodGroup::Shrink, 10),
-                _     => unreachable!(),
+    // nothing that stays put on this tile; critters wander off again
+    fn is_unclaimed(&self, pos: Position) -> bool {
+        return self.topology.contains(pos)
+            && !self.snake.is_in_snake(pos)
+            && !self.topology.is_portal(pos)
+            && !self.foods.contains(pos);
+    }
+
+    // Put a critter on a random free tile away from the snake's head.
+    // Returns false if no room could be found.
+    pub fn spawn_critter(&mut self, kind: CritterKind) -> bool {
+        // measured the way the snake would go, over edges and round walls
+        let near = self.topology.distances_within(self.snake.head_pos(), CRITTER_SPAWN_DISTANCE);
+        for _ in 0..100 {
+            let pos: Position = [self.rng.gen_range(0..self.map_size.x), self.rng.gen_range(0..self.map_size.y)].into();
+            if near[pos.x][pos.y].is_some_and(|d| d < CRITTER_SPAWN_DISTANCE) || !self.is_free(pos) {
+                continue;
+            }
+            // bouncers go diagonally, patrols any way the grid has
+            let headings = match kind {
+                CritterKind::Bouncer => &[Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight],
+                _ => self.topology.grid().directions(),
             };
-            self.add_food(food);
+            let heading = headings[self.rng.gen_range(0..headings.len())];
+            self.critters.push(Critter::new(kind, pos, heading));
+            return true;
         }
+        return false;
+    }
 
+    // move every food in range of the head one cell towards it
+    fn pull_food(&mut self) {
+        let topology = &self.topology;
+        let distances = topology.distances_within(self.snake.head_pos(), MAGNET_RANGE);
+        for index in 0..self.foods.len() {
+            let pos = self.foods[index].pos;
+            let distance = match distances[pos.x][pos.y] {
+                Some(distance) if distance > 0 => distance,
+                _ => continue,
+            };
+            // one step nearer the head; food stops when it reaches the snake
+            // or bumps into anything else
+            let next = topology.grid().directions().iter()
+                .filter_map(|dir| topology.step(pos, *dir))
+                .find(|next| distances[next.x][next.y] == Some(distance-1) && self.is_free(*next));
+            if let Some(next) = next {
+                self.foods.move_to(index, next);
+            }
+        }
+    }
 
+    // Link two tiles with a portal. Returns false if either tile is already
+    // taken by the snake, food or another portal.
+    pub fn add_portal<T: Into<Position>>(&mut self, a: T, b: T) -> bool {
+        let (a, b) = (a.into(), b.into());
+        let taken = |pos: Position| !self.is_free(pos);
+        if a == b || taken(a) || taken(b) {
+            return false;
+        }
+        self.topology.add_portal(Portal::new(a, b));
+        return true;
     }
 
-    fn add_food(&mut self, new_food: Food) {
-        let mut present = false;
-        self.foods.iter().for_each(|f| {
-            if *f==new_food {
-                present = true;
+    // place up to `count` portal pairs on random free tiles
+    pub fn add_random_portals(&mut self, count: usize) {
+        let mut placed = 0;
+        // give up eventually on crowded maps
+        for _ in 0..count*100 {
+            if placed == count {
+                break;
+            }
+            let a = [self.rng.gen_range(0..self.map_size.x), self.rng.gen_range(0..self.map_size.y)];
+            let b = [self.rng.gen_range(0..self.map_size.x), self.rng.gen_range(0..self.map_size.y)];
+            if self.add_portal(a, b) {
+                placed += 1;
             }
-        });
-        if !present {
-            self.foods.push(new_food)
         }
     }
 
-    pub fn get_render_map(&self) -> RenderData {
-        let mut map = vec![vec![Item::Nothing; self.map_size.x];self.map_size.y];
+    // every tile with nothing on it
+    fn free_cells(&self) -> Vec<Position> {
+        let mut cells = self.topology.cells();
+        cells.retain(|pos| self.is_free(*pos));
+        return cells;
+    }
+
+    // A free tile picked at random, or None if there aren't any. Picking
+    // tiles until one is free is quick until the board fills up, and only
+    // then are the free tiles all found.
+    fn random_free_cell(&mut self) -> Option<Position> {
+        for _ in 0..FREE_CELL_GUESSES {
+            let pos: Position = [self.rng.gen_range(0..self.map_size.x), self.rng.gen_range(0..self.map_size.y)].into();
+            if self.is_free(pos) {
+                return Some(pos);
+            }
+        }
+        let free = self.free_cells();
+        if free.is_empty() {
+            return None;
+        }
+        return Some(free[self.rng.gen_range(0..free.len())]);
+    }
+
+    // Nowhere left for food to go and nothing left to eat, so the snake
+    // has filled the board.
+    fn is_board_full(&self) -> bool {
+        let (width, height) = (self.map_size.x, self.map_size.y);
+        return self.foods.is_empty() && !(0..width).any(|x| (0..height).any(|y| self.is_unclaimed([x,y].into())));
+    }
+
+    // Change the shape of the board. This should come before anything is
+    // put on it, and moves the snake to the middle if it would be in a wall
+    // or on a portal.
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.map_size = topology.size();
+        if self.snake.iter_segments().any(|seg| !topology.contains(seg.pos) || topology.is_portal(seg.pos)) {
+            let policy = self.snake.collision();
+            self.snake = Snake::new(topology.start(), self.map_size);
+            self.snake.set_collision(policy);
+        }
+        self.snake.fit_topology(&topology);
+        self.topology = topology;
+        self.route = None;
+        self.foods.resize(self.map_size);
+        // the walls have moved, so the next board starts again
+        self.board = Rc::default();
+    }
+
+    pub fn set_spawn_rules(&mut self, rules: SpawnRules) {
+        self.spawn = rules;
+    }
+
+    pub fn set_target_length(&mut self, target: Option<usize>) {
+        self.target_length = target;
+    }
+
+    pub fn score(&self) -> usize {
+        return self.scorer.total();
+    }
+
+    pub fn score_breakdown(&self) -> ScoreBreakdown {
+        return self.scorer.breakdown;
+    }
+
+    pub fn set_scoring_rules(&mut self, rules: ScoringRules) {
+        self.scorer.rules = rules;
+    }
+
+    // Replay the same sequence of random events for the same seed, given
+    // the same moves.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn set_collision_policy(&mut self, policy: CollisionPolicy) {
+        self.snake.set_collision(policy);
+    }
+
+    pub fn set_mode(&mut self, mode: Box<dyn GameMode>) {
+        self.mode = mode;
+    }
+
+    pub fn mode_name(&self) -> String {
+        return self.mode.name();
+    }
 
+    pub fn snake_length(&self) -> usize {
+        return self.snake.iter_segments().count();
+    }
+
+    fn has_won(&self) -> bool {
+        if let Some(target) = self.target_length {
+            if self.snake_length() >= target {
+                return true;
+            }
+        }
+        return self.is_board_full();
+    }
+
+    // filled the whole board without ever shrinking
+    pub fn is_perfect(&self) -> bool {
+        return !self.shrunk && self.is_board_full();
+    }
+
+    pub fn gen_food(&mut self) {
+        if self.rng.gen_range(0..4) != 0 {
+            return;
+        }
+        if let Some(max) = self.spawn.max_food {
+            if self.foods.len() >= max {
+                return;
+            }
+        }
+        let picked = match self.spawn.strategy {
+            SpawnStrategy::Uniform => self.random_free_cell(),
+            strategy => {
+                let food_positions: Vec<Position> = self.foods.iter().map(|f| f.pos).collect();
+                strategy.pick(&self.free_cells(), self.snake.head_pos(), &food_positions, self.map_size, &mut self.rng)
+            },
+        };
+        let food_pos = match picked {
+            Some(pos) => pos,
+            None      => return,
+        };
+
+        // 6 in grow, 2 shrink, 1 power-up and however much poison the mode wants
+        let poison = self.mode.poison_weight(self.ticks);
+        let food = match self.rng.gen_range(0..9+poison) {
+            0..=5 => Food::new(food_pos, FoodGroup::Grow),
+            6..=7 => Food::new_with_lifetime(food_pos, FoodGroup::Shrink, 10),
+            8     => {
+                let kind = PowerUp::ALL[self.rng.gen_range(0..PowerUp::ALL.len())];
+                Food::new_with_lifetime(food_pos, FoodGroup::PowerUp(kind), 15)
+            },
+            _     => Food::new_with_lifetime(food_pos, FoodGroup::Poison, 10),
+        };
+        self.foods.push(food);
+    }
+
+    // a board with nothing but the walls on it
+    fn empty_board(&self) -> RenderMap {
+        let mut map = vec![vec![Item::Nothing; self.map_size.y];self.map_size.x];
+        self.topology.walls().iter().for_each(|wall| {
+            map[wall.x][wall.y] = Item::Wall;
+        });
+        return map;
+    }
+
+    // Everything on the board besides the walls, in the order it's drawn,
+    // so whatever comes later covers what's under it
+    fn for_each_item<F: FnMut(Position, Item)>(&self, mut draw: F) {
         // fill in the snake
         self.snake.iter_segments().enumerate().for_each(|(index,seg)| {
             if index==0 {
-                map[seg.pos.x][seg.pos.y] = Item::SnakeHead;
+                draw(seg.pos, Item::SnakeHead);
             } else {
-                map[seg.pos.x][seg.pos.y] = Item::Snake;
+                draw(seg.pos, Item::Snake);
             }
         });
 
+        self.topology.portals().iter().for_each(|portal| {
+            draw(portal.a, Item::Portal);
+            draw(portal.b, Item::Portal);
+        });
+
         // fill in the food
         self.foods.iter().for_each(|food| {
-            map[food.pos.x][food.pos.y] = Item::Food(food.group);
+            draw(food.pos, Item::Food(food.group));
+        });
+
+        self.critters.iter().for_each(|critter| {
+            draw(critter.pos, Item::Critter(critter.kind));
         });
+    }
+
+    pub fn get_render_map(&self) -> RenderData {
+        let mut map = self.empty_board();
+        self.for_each_item(|pos, item| map[pos.x][pos.y] = item);
+        return self.render_data(Rc::new(map), None);
+    }
+
+    // The same as `get_render_map`, along with the cells that have changed
+    // since the last call. The board is kept from one call to the next and
+    // only those cells are touched, so as long as the last lot of render
+    // data has gone, nothing is built afresh.
+    pub fn get_render_changes(&mut self) -> RenderData {
+        let changed = self.update_board();
+        return self.render_data(Rc::clone(&self.board), changed);
+    }
+
+    // Bring the kept board up to date, returning the cells that changed, or
+    // None if it had to be started again
+    fn update_board(&mut self) -> Option<Vec<[usize;2]>> {
+        let mut drawn = mem::take(&mut self.drawn);
+        if self.board.len() != self.map_size.x || self.board.first().map_or(0, |column| column.len()) != self.map_size.y {
+            let mut map = self.empty_board();
+            drawn.clear();
+            self.for_each_item(|pos, item| {
+                map[pos.x][pos.y] = item;
+                drawn.push(pos);
+            });
+            self.board = Rc::new(map);
+            self.drawn = drawn;
+            return None;
+        }
+
+        let mut board = mem::take(&mut self.board);
+        let map = Rc::make_mut(&mut board);
+        // take everything off, remembering what was there
+        let mut before: Vec<([usize;2], Item)> = drawn.iter().map(|pos| ([pos.x, pos.y], map[pos.x][pos.y])).collect();
+        for ([x, y], _) in before.iter() {
+            map[*x][*y] = Item::Nothing;
+        }
+        drawn.clear();
+        self.for_each_item(|pos, item| {
+            map[pos.x][pos.y] = item;
+            drawn.push(pos);
+        });
+
+        // a cell has changed if it's not what it was, or wasn't anything
+        before.sort_unstable_by_key(|(cell, _)| *cell);
+        let mut changed: Vec<[usize;2]> = before.iter().map(|(cell, _)| *cell).chain(drawn.iter().map(|pos| [pos.x, pos.y])).collect();
+        changed.sort_unstable();
+        changed.dedup();
+        changed.retain(|&[x, y]| {
+            let was = match before.binary_search_by_key(&[x, y], |(cell, _)| *cell) {
+                Ok(index) => before[index].1,
+                Err(_)    => Item::Nothing,
+            };
+            map[x][y] != was
+        });
+
+        self.board = board;
+        self.drawn = drawn;
+        return Some(changed);
+    }
+
+    fn render_data(&self, rm: Rc<RenderMap>, changed: Option<Vec<[usize;2]>>) -> RenderData {
+        let snake = self.snake.iter_segments().map(|seg| [seg.pos.x, seg.pos.y]).collect();
 
         return RenderData {
-            rm: map,
-            score: self.score,
-            log_data: [Some(format!("> score: {}\n",self.score)), None, None, None],
+            rm,
+            changed,
+            snake,
+            score: self.score(),
+            combo: self.scorer.combo(),
+            speed: self.snake.speed(),
+            status: self.mode.status(self.ticks),
+            effects: self.snake.effects().iter().copied().collect(),
+            hex: self.topology.grid().shape() == grid::Shape::Hex,
+            planned: self.snake.get_next_head_pos(&self.topology).map(|pos| [pos.x, pos.y]),
         };
     }
 }
 
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Item {
     Snake,
     SnakeHead,
     Food(FoodGroup),
+    Portal,
+    Critter(CritterKind),
+    Wall,
     Nothing,
 }
 
@@ -191,9 +813,9 @@ mod test {
     #[test]
     fn init_gs() {
         let gs = GameState::new([10,10],[4,4]);
-        assert_eq!(gs.foods,     Vec::new());
+        assert!(gs.foods.is_empty());
         assert_eq!(gs.map_size,  Size{x:10,y:10});
-        assert_eq!(gs.score,     0);
+        assert_eq!(gs.score(),   0);
     }
 
     #[test]
@@ -202,7 +824,7 @@ mod test {
         for _i in 0..10 {
             gs.update(SnakeControl::None, false);
         }
-        assert_eq!(gs.snake.get_next_head_pos(), [4,5].into());
+        assert_eq!(gs.snake.get_next_head_pos(&gs.topology), Some([4,5].into()));
     }
 
     #[test]
@@ -210,12 +832,12 @@ mod test {
         let mut gs = GameState::new([10,10],[4,4]);
         let food_count_ref = [1,1,1,1,1,1,1,1,0,0];
         gs.gen_food();
-        for i in 0..10 {
+        for food_count in food_count_ref.iter() {
             gs.update(SnakeControl::None, false);
-            println!("({:?}-{:?})",food_count_ref[i], gs.foods.len());
+            println!("({:?}-{:?})",food_count, gs.foods.len());
             // assert_eq!(food_count_ref[i], gs.foods.len());
         }
-        assert_eq!(gs.snake.get_next_head_pos(), [4,5].into());
+        assert_eq!(gs.snake.get_next_head_pos(&gs.topology), Some([4,5].into()));
     }
 
     #[test]
@@ -227,7 +849,505 @@ mod test {
             gs.update(SnakeControl::None, true);
             // assert_eq!(food_count_ref[i], gs.foods.len());
         }
-        // assert_eq!(gs.snake.get_next_head_pos(), [4,5].into());
+        // assert_eq!(gs.snake.get_next_head_pos(&gs.topology), [4,5].into());
+    }
+
+    #[test]
+    fn walled_boards() {
+        let level = "wrap rectangle\n#...\n....\n...#\n";
+        let mut gs = GameState::new([10,10],[0,0]);
+        gs.set_topology(Topology::from_level(level).unwrap());
+        // [0,0] was open, so the snake stays where it was
+        assert_eq!(gs.snake.head_pos(), [0,0].into());
+        assert_eq!(gs.free_cells().len(), 9);
+        for _ in 0..40 {
+            gs.gen_food();
+        }
+        assert!(gs.foods.iter().all(|f| f.pos != [0,2].into() && f.pos != [3,0].into()));
+        let rd = gs.get_render_map();
+        assert!(matches!(rd.rm[3][0], Item::Wall));
+        assert_eq!(rd.rm.len(), 4);
+
+        // the snake dies going off the edge, except in zen mode
+        gs.foods = Foods::new(gs.map_size);
+        gs.snake.set_dir(Direction::Left, &gs.topology);
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Dead(Reason::Wall));
+        gs.set_mode(mode::from_name("zen").unwrap());
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.snake.head_pos(), [0,0].into());
+
+        // a snake that would be in a wall moves to the middle
+        let mut gs = GameState::new([10,10],[0,0]);
+        gs.set_topology(Topology::from_level(LEVELS[0].1).unwrap());
+        assert_eq!(gs.snake.head_pos(), [7,7].into());
+
+        // portals drawn into the level take the snake across
+        let mut gs = GameState::new([10,10],[1,0]);
+        gs.set_topology(Topology::from_level("wrap rectangle\n.a.\n...\n..a\n").unwrap());
+        assert_eq!(gs.snake.head_pos(), [1,0].into());
+        gs.update(SnakeControl::Right, false);
+        assert_eq!(gs.snake.head_pos(), [1,2].into());
+        assert!(matches!(gs.get_render_map().rm[2][0], Item::Portal));
+    }
+
+    #[test]
+    fn relative_controls() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        let relative = ControlScheme::Relative;
+        // heading up, so a left turn goes left and another goes down
+        gs.update(relative.apply(SnakeControl::Left), false);
+        assert_eq!(gs.snake.head_pos(), [3,4].into());
+        gs.update(relative.apply(SnakeControl::Left), false);
+        assert_eq!(gs.snake.head_pos(), [3,3].into());
+        // up has no meaning any more
+        gs.update(relative.apply(SnakeControl::Up), false);
+        assert_eq!(gs.snake.head_pos(), [3,2].into());
+        gs.update(relative.apply(SnakeControl::Right), false);
+        assert_eq!(gs.snake.head_pos(), [2,2].into());
+
+        assert_eq!(ControlScheme::Absolute.apply(SnakeControl::Up), SnakeControl::Up);
+        for name in ControlScheme::NAMES.iter() {
+            assert_eq!(ControlScheme::from_str(name).unwrap().to_string(), *name);
+        }
+        assert!(ControlScheme::from_str("telepathic").is_err());
+    }
+
+    #[test]
+    fn hex_game() {
+        let mut gs = GameState::new([6,6],[2,2]);
+        let mut hex = Topology::new(Wrap::Torus, [6,6].into());
+        hex.set_grid(&grid::HEX);
+        gs.set_topology(hex);
+        gs.foods.push(Food::new([1,3], FoodGroup::Grow));
+        assert_eq!(gs.update(SnakeControl::UpLeft, false), SnakeState::Alive);
+        assert_eq!(gs.snake_length(), 2);
+        // straight down isn't a way on a hex grid, so the snake carries on
+        assert_eq!(gs.update(SnakeControl::Down, false), SnakeState::Alive);
+        assert_eq!(gs.snake.head_pos(), [0,4].into());
+        assert!(gs.get_render_map().hex);
+    }
+
+    #[test]
+    fn steering_between_ticks() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        assert_eq!(gs.get_render_map().planned, Some([4,5]));
+        // the new heading shows at once, but nothing moves until the tick
+        gs.steer(SnakeControl::Right);
+        assert_eq!(gs.snake.head_pos(), [4,4].into());
+        assert_eq!(gs.get_render_map().planned, Some([5,4]));
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.snake.head_pos(), [5,4].into());
+        // nowhere to go shows nothing
+        gs.set_topology(Topology::new(Wrap::Rectangle, [6,10].into()));
+        assert_eq!(gs.get_render_map().planned, None);
+    }
+
+    #[test]
+    fn render_changes() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.foods.push(Food::new([4,6], FoodGroup::Grow));
+        let rd = gs.get_render_changes();
+        assert_eq!(rd.changed, None);
+        let mut before = (*rd.rm).clone();
+        drop(rd);
+        // the head runs over the food on the second tick
+        for _ in 0..3 {
+            gs.update(SnakeControl::None, false);
+            let rd = gs.get_render_changes();
+            let full = gs.get_render_map();
+            assert_eq!(rd.rm, full.rm);
+            let mut expected = Vec::new();
+            for (x, (was, now)) in before.iter().zip(full.rm.iter()).enumerate() {
+                for (y, (was, now)) in was.iter().zip(now.iter()).enumerate() {
+                    if was != now {
+                        expected.push([x,y]);
+                    }
+                }
+            }
+            assert!(!expected.is_empty());
+            assert_eq!(rd.changed, Some(expected));
+            before = (*rd.rm).clone();
+        }
+        // nothing moved, nothing to draw
+        assert_eq!(gs.get_render_changes().changed, Some(Vec::new()));
+        // new walls mean starting again
+        gs.set_topology(Topology::new(Wrap::Rectangle, [10,10].into()));
+        assert_eq!(gs.get_render_changes().changed, None);
+    }
+
+    #[test]
+    fn steer_to_clicked_cell() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.set_topology(Topology::new(Wrap::Rectangle, [10,10].into()));
+        // heading up, so a cell below means going round
+        gs.update(SnakeControl::Goto([4,2]), false);
+        assert_eq!(gs.snake.head_pos(), [5,4].into());
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.snake.head_pos(), [5,3].into());
+        gs.update(SnakeControl::None, false);
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.snake.head_pos(), [4,2].into());
+        // there, so the snake carries on as it was
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.target, None);
+        assert_eq!(gs.snake.head_pos(), [3,2].into());
+
+        // the way there is only worked out once
+        assert_eq!(gs.route.as_ref().map(|(to, _)| *to), Some([4,2].into()));
+
+        // a key takes over from the mouse
+        gs.update(SnakeControl::Goto([9,2]), false);
+        assert_eq!(gs.route.as_ref().map(|(to, _)| *to), Some([9,2].into()));
+        gs.update(SnakeControl::Down, false);
+        assert_eq!(gs.target, None);
+        // a new board means working it out again
+        gs.set_topology(Topology::new(Wrap::Torus, [10,10].into()));
+        assert!(gs.route.is_none());
+        // and walls can't be aimed at
+        let mut gs = GameState::new([10,10],[0,0]);
+        gs.set_topology(Topology::from_level(LEVELS[0].1).unwrap());
+        gs.set_target([0,0].into());
+        assert_eq!(gs.target, None);
+    }
+
+    #[test]
+    fn add_portals() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.foods.push(Food::new([0,0], FoodGroup::Grow));
+        assert!(gs.add_portal([1,1],[5,5]));
+        assert!(!gs.add_portal([1,1],[6,6]));
+        assert!(!gs.add_portal([4,4],[6,6]));
+        assert!(!gs.add_portal([0,0],[6,6]));
+        assert!(!gs.add_portal([6,6],[6,6]));
+        assert!(!gs.add_portal([6,6],[10,6]));
+        gs.add_random_portals(3);
+        assert_eq!(gs.topology.portals().len(), 4);
+    }
+
+    #[test]
+    fn eat_food_through_portal() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        assert!(gs.add_portal([4,5],[8,8]));
+        gs.foods.push(Food::new([8,8], FoodGroup::Grow));
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.score(), 1);
+        assert_eq!(gs.snake.head_pos(), [8,8].into());
+        assert_eq!(gs.snake.iter_segments().count(), 2);
+    }
+
+    #[test]
+    fn food_avoids_portals() {
+        let mut gs = GameState::new([4,1],[0,0]);
+        assert!(gs.add_portal([1,0],[2,0]));
+        for _ in 0..40 {
+            gs.gen_food();
+        }
+        // the only free tile is [3,0]
+        assert!(gs.foods.iter().all(|f| f.pos == [3,0].into()));
+    }
+
+    #[test]
+    fn food_never_stacks() {
+        let mut gs = GameState::new([3,1],[0,0]);
+        for _ in 0..200 {
+            gs.gen_food();
+        }
+        assert_eq!(gs.foods.len(), 2);
+        assert_ne!(gs.foods[0].pos, gs.foods[1].pos);
+    }
+
+    #[test]
+    fn same_seed_same_food() {
+        let play = || {
+            let mut gs = GameState::new([10,10],[4,4]);
+            gs.set_seed(7);
+            gs.add_random_portals(1);
+            for _ in 0..20 {
+                gs.update(SnakeControl::Right, true);
+            }
+            return (gs.foods, gs.topology.portals().to_vec());
+        };
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn max_food_count() {
+        let mut gs = GameState::new([10,10],[0,0]);
+        gs.set_spawn_rules(SpawnRules { strategy: SpawnStrategy::AwayFromHead(3), max_food: Some(2) });
+        for _ in 0..200 {
+            gs.gen_food();
+        }
+        assert_eq!(gs.foods.len(), 2);
+    }
+
+    #[test]
+    fn full_board() {
+        let mut gs = GameState::new([2,1],[0,0]);
+        gs.snake = Snake::from_segments(vec![[0,0].into(),[1,0].into()], Direction::Right, gs.map_size);
+        // used to spin forever looking for a free tile
+        gs.gen_food();
+        assert!(gs.foods.is_empty());
+        assert!(gs.is_board_full());
+
+        let mut gs = GameState::new([2,1],[0,0]);
+        gs.foods.push(Food::new([1,0], FoodGroup::Grow));
+        assert!(!gs.is_board_full());
+
+        // a critter on the last open tile doesn't fill it
+        let mut gs = GameState::new([2,1],[0,0]);
+        gs.snake = Snake::from_segments(vec![[0,0].into()], Direction::Right, gs.map_size);
+        gs.critters.push(Critter::new(CritterKind::Mouse, [1,0], Direction::Up));
+        assert!(!gs.is_board_full());
+        assert!(!gs.has_won());
+    }
+
+    #[test]
+    fn win_by_filling_board() {
+        let mut gs = GameState::new([2,1],[0,0]);
+        gs.snake = Snake::from_segments(vec![[0,0].into()], Direction::Right, gs.map_size);
+        gs.foods.push(Food::new([1,0], FoodGroup::Grow));
+        assert_eq!(gs.update(SnakeControl::None, true), SnakeState::Won);
+        assert!(gs.is_perfect());
+
+        // shrinking on the way spoils a perfect game
+        let mut gs = GameState::new([2,1],[0,0]);
+        gs.snake = Snake::from_segments(vec![[0,0].into()], Direction::Right, gs.map_size);
+        gs.foods.push(Food::new([1,0], FoodGroup::Grow));
+        gs.shrunk = true;
+        assert_eq!(gs.update(SnakeControl::None, true), SnakeState::Won);
+        assert!(!gs.is_perfect());
+    }
+
+    #[test]
+    fn win_by_target_length() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.set_target_length(Some(3));
+        gs.foods.push(Food::new([4,5], FoodGroup::Grow));
+        gs.foods.push(Food::new([4,6], FoodGroup::Grow));
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Won);
+        assert_eq!(gs.snake_length(), 3);
+        assert!(!gs.is_perfect());
+    }
+
+    #[test]
+    fn hazard_kills_on_contact() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.critters.push(Critter::new(CritterKind::Bouncer, [4,5], Direction::UpRight));
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Dead(Reason::Hazard));
+
+        // patrol walks into the head
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.critters.push(Critter::new(CritterKind::Patrol, [6,6], Direction::Left));
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Dead(Reason::Hazard));
+    }
+
+    #[test]
+    fn hazard_bounces_off_body() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        let body = vec![[4,4].into(),[4,3].into(),[4,2].into(),[4,1].into(),[4,0].into()];
+        gs.snake = Snake::from_segments(body, Direction::Up, gs.map_size);
+        gs.critters.push(Critter::new(CritterKind::Patrol, [6,3], Direction::Left));
+        gs.update(SnakeControl::None, false);
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.critters[0].pos, [6,3].into());
+    }
+
+    #[test]
+    fn catch_mouse() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.critters.push(Critter::new(CritterKind::Mouse, [4,5], Direction::Up));
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.score(), ScoringRules::default().mouse as usize);
+        assert_eq!(gs.snake.iter_segments().count(), 2);
+        // a new mouse turns up somewhere else
+        assert_eq!(gs.critters.len(), 1);
+        assert!(!gs.snake.is_in_snake(gs.critters[0].pos));
+    }
+
+    #[test]
+    fn spawn_critters_away_from_snake() {
+        // in the corner of a torus, so the nearest cells are over the edges
+        let mut gs = GameState::new([6,6],[0,0]);
+        assert_eq!(gs.topology.wrap(), Wrap::Torus);
+        for kind in CritterKind::ALL.iter().cycle().take(12) {
+            gs.spawn_critter(*kind);
+        }
+        let distances = gs.topology.distances(gs.snake.head_pos());
+        for critter in gs.critters.iter() {
+            let pos = critter.pos;
+            assert!(distances[pos.x][pos.y].unwrap() >= CRITTER_SPAWN_DISTANCE, "{:?}", pos);
+            assert_eq!(gs.critters.iter().filter(|c| c.pos == critter.pos).count(), 1);
+        }
+    }
+
+    #[test]
+    fn multiplier_scores_food() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.snake.effects_mut().apply(PowerUp::ScoreMultiplier);
+        gs.foods.push(Food::new([4,5], FoodGroup::Grow));
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.score(), 2);
+    }
+
+    #[test]
+    fn zen_trims_tail() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.set_mode(Box::new(mode::Zen));
+        // head at [4,4] heading down into its own body at [4,3]
+        let body = vec![[4,4].into(),[5,4].into(),[5,3].into(),[4,3].into(),[3,3].into(),[2,3].into()];
+        gs.snake = Snake::from_segments(body, Direction::Down, gs.map_size);
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.snake.head_pos(), [4,3].into());
+        assert_eq!(gs.snake_length(), 3);
+
+        gs.foods.push(Food::new([4,2], FoodGroup::Poison));
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.snake.head_pos(), [4,2].into());
+
+        // a hazard in the way is knocked off and the snake moves on
+        gs.critters.push(Critter::new(CritterKind::Bouncer, [4,1], Direction::Up));
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.snake.head_pos(), [4,1].into());
+        assert!(gs.critters.is_empty());
+    }
+
+    #[test]
+    fn self_cut() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        let body = vec![[4,4].into(),[5,4].into(),[5,3].into(),[4,3].into(),[3,3].into(),[2,3].into()];
+        gs.snake = Snake::from_segments(body, Direction::Down, gs.map_size);
+        gs.set_collision_policy(CollisionPolicy::SelfCut);
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.snake.head_pos(), [4,3].into());
+        assert_eq!(gs.snake_length(), 3);
+        assert_eq!(gs.score_breakdown().penalties, 3);
+        assert!(gs.shrunk);
+
+        // strict and classic rules both kill
+        for policy in [CollisionPolicy::Strict, CollisionPolicy::Classic].iter() {
+            let mut gs = GameState::new([10,10],[4,4]);
+            let body = vec![[4,4].into(),[5,4].into(),[5,3].into(),[4,3].into(),[3,3].into(),[2,3].into()];
+            gs.snake = Snake::from_segments(body, Direction::Down, gs.map_size);
+            gs.set_collision_policy(*policy);
+            assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Dead(Reason::Collision), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn time_attack_ends() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.set_mode(mode::from_name("time-attack:2").unwrap());
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::Alive);
+        assert_eq!(gs.get_render_map().status, Some("time:1".to_string()));
+        assert_eq!(gs.update(SnakeControl::None, false), SnakeState::TimeUp);
+    }
+
+    #[test]
+    fn survival_scores_ticks() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.set_mode(Box::new(mode::Survival));
+        for _ in 0..5 {
+            gs.update(SnakeControl::None, false);
+        }
+        assert_eq!(gs.score(), 5);
+    }
+
+    #[test]
+    fn food_values_and_combos() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.foods.push(Food::new([4,5], FoodGroup::Grow));
+        gs.foods.push(Food::new([4,6], FoodGroup::Grow));
+        gs.foods.push(Food::new_with_lifetime([4,7], FoodGroup::Shrink, 9));
+        gs.update(SnakeControl::None, false);
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.score(), 3);
+        assert_eq!(gs.scorer.combo(), 2);
+        // shrink food costs a point and ends the combo
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.score_breakdown(), ScoreBreakdown { food: 2, combo: 1, penalties: 1, ..Default::default() });
+        assert_eq!(gs.scorer.combo(), 0);
+    }
+
+    #[test]
+    fn boost_and_brake() {
+        let mut gs = GameState::new([20,20],[4,4]);
+        gs.scorer.breakdown.food = 10;
+        for _ in 0..SPEED_UNIT {
+            gs.update(SnakeControl::Faster, false);
+        }
+        // gone up to twice normal speed, paying a point a tick on the way
+        assert_eq!(gs.snake.speed(), 2*SPEED_UNIT);
+        assert_eq!(gs.score(), 10-SPEED_UNIT);
+        let before = gs.snake.head_pos();
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.snake.head_pos(), [before.x, before.y+2].into());
+
+        // down to the slowest, a quarter cell a tick
+        for _ in 0..2*SPEED_UNIT {
+            gs.update(SnakeControl::Slower, false);
+        }
+        let mut moves = 0;
+        for _ in 0..4*SPEED_UNIT {
+            let before = gs.snake.head_pos();
+            gs.update(SnakeControl::None, false);
+            if gs.snake.head_pos() != before {
+                moves += 1;
+            }
+        }
+        assert_eq!(moves, 4);
+    }
+
+    #[test]
+    fn speed_boost_moves_twice() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.snake.effects_mut().apply(PowerUp::SpeedBoost);
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.snake.head_pos(), [4,6].into());
+    }
+
+    #[test]
+    fn slow_motion_moves_every_other_tick() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.snake.effects_mut().apply(PowerUp::SlowMotion);
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.snake.head_pos(), [4,4].into());
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.snake.head_pos(), [4,5].into());
+    }
+
+    #[test]
+    fn effects_expire_during_play() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.snake.effects_mut().apply(PowerUp::Ghost);
+        for _ in 0..PowerUp::Ghost.duration() {
+            assert!(gs.snake.effects().is_active(PowerUp::Ghost));
+            gs.update(SnakeControl::None, false);
+        }
+        assert!(!gs.snake.effects().is_active(PowerUp::Ghost));
+    }
+
+    #[test]
+    fn magnet_pulls_food() {
+        let mut gs = GameState::new([10,10],[4,4]);
+        gs.snake.effects_mut().apply(PowerUp::Magnet);
+        gs.foods.push(Food::new([7,5], FoodGroup::Grow));
+        gs.foods.push(Food::new([0,0], FoodGroup::Grow));
+        gs.update(SnakeControl::None, false);
+        // head is now at [4,5]
+        assert_eq!(gs.foods[0].pos, [6,5].into());
+        assert_eq!(gs.foods[1].pos, [0,0].into());
+
+        // pulled over the edge, and stopped by a critter in the way
+        let mut gs = GameState::new([10,10],[1,4]);
+        gs.snake.effects_mut().apply(PowerUp::Magnet);
+        gs.foods.push(Food::new([8,5], FoodGroup::Grow));
+        gs.foods.push(Food::new([4,5], FoodGroup::Grow));
+        gs.critters.push(Critter::new(CritterKind::Mouse, [3,5], Direction::Up));
+        gs.update(SnakeControl::None, false);
+        assert_eq!(gs.foods[0].pos, [9,5].into());
+        assert_eq!(gs.foods[1].pos, [4,5].into());
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+use super::sprites::{Reason, SnakeState};
+
+// The rules that differ between game modes. `GameState` asks its mode at
+// each point where they can differ, so modes don't need to touch the core
+// update loop.
+pub trait GameMode {
+    fn name(&self) -> String;
+
+    // Checked once a tick, after everything has moved. Returning a state
+    // other than Alive ends the game.
+    fn check(&self, _ticks: usize) -> SnakeState {
+        return SnakeState::Alive;
+    }
+
+    // points for getting through a tick alive
+    fn tick_points(&self) -> usize {
+        return 0;
+    }
+
+    // how likely new food is to be poison, out of the 6 for grow, 2 for
+    // shrink and 1 for a power-up
+    fn poison_weight(&self, _ticks: usize) -> usize {
+        return 2;
+    }
+
+    // whether the snake gets away with what would have killed it
+    fn forgives(&self, _reason: &Reason) -> bool {
+        return false;
+    }
+
+    // shown in the HUD next to the score
+    fn status(&self, _ticks: usize) -> Option<String> {
+        return None;
+    }
+}
+
+// The original game: eat, grow, don't crash
+pub struct Classic;
+
+impl GameMode for Classic {
+    fn name(&self) -> String {
+        return "classic".to_string();
+    }
+}
+
+// Score as much as possible before time runs out
+pub struct TimeAttack {
+    pub ticks: usize,
+}
+
+impl GameMode for TimeAttack {
+    fn name(&self) -> String {
+        return format!("time-attack:{}", self.ticks);
+    }
+
+    fn check(&self, ticks: usize) -> SnakeState {
+        if ticks >= self.ticks {
+            return SnakeState::TimeUp;
+        }
+        return SnakeState::Alive;
+    }
+
+    fn status(&self, ticks: usize) -> Option<String> {
+        return Some(format!("time:{}", self.ticks.saturating_sub(ticks)));
+    }
+}
+
+// Stay alive as long as possible while the board fills up with poison
+pub struct Survival;
+
+// ticks between each extra share of poison
+const SURVIVAL_POISON_RATE: usize = 20;
+const SURVIVAL_MAX_POISON: usize = 18;
+
+impl GameMode for Survival {
+    fn name(&self) -> String {
+        return "survival".to_string();
+    }
+
+    fn tick_points(&self) -> usize {
+        return 1;
+    }
+
+    fn poison_weight(&self, ticks: usize) -> usize {
+        return (2+ticks/SURVIVAL_POISON_RATE).min(SURVIVAL_MAX_POISON);
+    }
+}
+
+// Nothing can kill the snake; running into itself just loses the tail
+pub struct Zen;
+
+impl GameMode for Zen {
+    fn name(&self) -> String {
+        return "zen".to_string();
+    }
+
+    fn forgives(&self, _reason: &Reason) -> bool {
+        return true;
+    }
+}
+
+pub const NAMES: [&str; 4] = ["classic", "time-attack[:TICKS]", "survival", "zen"];
+
+// ticks in a time attack game if none are given, a minute at two ticks a second
+const DEFAULT_TIME_ATTACK: usize = 120;
+
+pub fn from_name(name: &str) -> Result<Box<dyn GameMode>, String> {
+    match name {
+        "classic"     => return Ok(Box::new(Classic)),
+        "survival"    => return Ok(Box::new(Survival)),
+        "zen"         => return Ok(Box::new(Zen)),
+        "time-attack" => return Ok(Box::new(TimeAttack { ticks: DEFAULT_TIME_ATTACK })),
+        _ => {},
+    }
+    if let Some(ticks) = name.strip_prefix("time-attack:") {
+        let ticks = usize::from_str(ticks).map_err(|_| format!("invalid time limit '{}'", ticks))?;
+        return Ok(Box::new(TimeAttack { ticks }));
+    }
+    return Err(format!("unknown game mode '{}', expected one of: {}", name, NAMES.join(", ")));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_modes() {
+        for name in ["classic", "time-attack:30", "survival", "zen"].iter() {
+            assert_eq!(from_name(name).unwrap().name(), *name);
+        }
+        assert_eq!(from_name("time-attack").unwrap().name(), "time-attack:120");
+        assert!(from_name("time-attack:soon").is_err());
+        assert!(from_name("tetris").is_err());
+    }
+
+    #[test]
+    fn time_attack_counts_down() {
+        let mode = TimeAttack { ticks: 10 };
+        assert_eq!(mode.status(4), Some("time:6".to_string()));
+        assert_eq!(mode.check(9), SnakeState::Alive);
+        assert_eq!(mode.check(10), SnakeState::TimeUp);
+    }
+
+    #[test]
+    fn survival_gets_more_poisonous() {
+        assert_eq!(Survival.poison_weight(0), Classic.poison_weight(0));
+        assert!(Survival.poison_weight(100) > Survival.poison_weight(0));
+        assert_eq!(Survival.poison_weight(10_000), SURVIVAL_MAX_POISON);
+    }
+}
@@ -0,0 +1,77 @@
+use super::util::{Position, Size};
+
+// Something kept for every cell of the board, found straight from a
+// position rather than by searching through whatever is on the board. The
+// owner changes it along with the things it stands for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occupancy<T> {
+    size: Size,
+    cells: Vec<T>,
+}
+
+impl<T: Copy + Default> Occupancy<T> {
+    pub fn new(size: Size) -> Self {
+        return Self { size, cells: vec![T::default(); size.x*size.y] };
+    }
+
+    pub fn size(&self) -> Size {
+        return self.size;
+    }
+
+    fn index(&self, pos: Position) -> Option<usize> {
+        if pos.x >= self.size.x || pos.y >= self.size.y {
+            return None;
+        }
+        return Some(pos.x*self.size.y+pos.y);
+    }
+
+    // cells off the board have nothing on them
+    pub fn get(&self, pos: Position) -> T {
+        return self.index(pos).map_or_else(T::default, |index| self.cells[index]);
+    }
+
+    pub fn set(&mut self, pos: Position, value: T) {
+        if let Some(index) = self.index(pos) {
+            self.cells[index] = value;
+        }
+    }
+}
+
+// How many things are on each cell, for things that can pile up, like a
+// ghost snake crossing itself
+impl Occupancy<usize> {
+    pub fn add(&mut self, pos: Position) {
+        self.set(pos, self.get(pos)+1);
+    }
+
+    pub fn remove(&mut self, pos: Position) {
+        self.set(pos, self.get(pos).saturating_sub(1));
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        return self.get(pos) > 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn count_things_on_cells() {
+        let mut occupied = Occupancy::new(Size { x: 3, y: 2 });
+        let pos: Position = [2,1].into();
+        assert!(!occupied.contains(pos));
+        occupied.add(pos);
+        occupied.add(pos);
+        occupied.remove(pos);
+        assert!(occupied.contains(pos));
+        occupied.remove(pos);
+        assert!(!occupied.contains(pos));
+        assert!(!occupied.contains([1,1].into()));
+        // off the board there's never anything
+        occupied.add([3,0].into());
+        assert!(!occupied.contains([3,0].into()));
+        assert_eq!(occupied.get([0,2].into()), 0);
+    }
+}
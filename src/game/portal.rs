@@ -0,0 +1,49 @@
+use super::util::Position;
+
+// A pair of linked tiles. Moving onto either one puts you on the other,
+// still heading the same way.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Portal {
+    pub a: Position,
+    pub b: Position,
+}
+
+impl Portal {
+    pub fn new<T: Into<Position>>(a: T, b: T) -> Self {
+        Self { a: a.into(), b: b.into() }
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        return pos == self.a || pos == self.b;
+    }
+
+    // where you come out if you step onto `pos`
+    pub fn exit(&self, pos: Position) -> Option<Position> {
+        if pos == self.a {
+            return Some(self.b);
+        } else if pos == self.b {
+            return Some(self.a);
+        }
+        return None;
+    }
+}
+
+// follow any portal at `pos` through to its twin
+pub fn warp(portals: &[Portal], pos: Position) -> Position {
+    return portals.iter().find_map(|p| p.exit(pos)).unwrap_or(pos);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn warp_through_portals() {
+        let portals = vec![Portal::new([1,1],[3,4]), Portal::new([0,2],[4,0])];
+        assert_eq!(warp(&portals, [1,1].into()), [3,4].into());
+        assert_eq!(warp(&portals, [3,4].into()), [1,1].into());
+        assert_eq!(warp(&portals, [4,0].into()), [0,2].into());
+        assert_eq!(warp(&portals, [2,2].into()), [2,2].into());
+        assert!(portals[0].contains([3,4].into()));
+        assert!(!portals[0].contains([0,2].into()));
+    }
+}
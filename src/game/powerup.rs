@@ -0,0 +1,208 @@
+use std::fmt;
+use std::str::FromStr;
+
+// Power-ups are eaten like food and give the snake a timed effect
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PowerUp {
+    // pass through your own body
+    Ghost,
+    // move two cells a tick
+    SpeedBoost,
+    // move every other tick
+    SlowMotion,
+    // multiply the score for each food eaten, stacks up to MAX_MULTIPLIER
+    ScoreMultiplier,
+    // pull nearby food towards the head
+    Magnet,
+}
+
+pub const MAX_MULTIPLIER: usize = 4;
+// picking up a power-up that is already running adds to its time, up to this
+// many times its normal duration
+const MAX_DURATION_FACTOR: usize = 3;
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 5] = [
+        PowerUp::Ghost,
+        PowerUp::SpeedBoost,
+        PowerUp::SlowMotion,
+        PowerUp::ScoreMultiplier,
+        PowerUp::Magnet,
+    ];
+
+    // how many ticks the effect lasts
+    pub fn duration(&self) -> usize {
+        match self {
+            PowerUp::Ghost           => 15,
+            PowerUp::SpeedBoost      => 20,
+            PowerUp::SlowMotion      => 20,
+            PowerUp::ScoreMultiplier => 30,
+            PowerUp::Magnet          => 25,
+        }
+    }
+}
+
+impl fmt::Display for PowerUp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PowerUp::Ghost           => "ghost",
+            PowerUp::SpeedBoost      => "speed",
+            PowerUp::SlowMotion      => "slow",
+            PowerUp::ScoreMultiplier => "multiplier",
+            PowerUp::Magnet          => "magnet",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for PowerUp {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PowerUp::ALL.iter().find(|p| p.to_string() == s).copied().ok_or_else(|| format!("unknown power-up '{}'", s))
+    }
+}
+
+// A power-up currently acting on a snake
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Effect {
+    pub kind: PowerUp,
+    // ticks left before it wears off
+    pub remaining: usize,
+    // only used by the score multiplier
+    pub level: usize,
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Effects {
+    active: Vec<Effect>,
+}
+
+impl Effects {
+    // Stacking rules:
+    //  - taking a power-up that is already active extends it, up to a cap
+    //  - speed boost and slow motion cancel each other out
+    //  - the score multiplier goes up a level and its time is reset
+    pub fn apply(&mut self, kind: PowerUp) {
+        let opposite = match kind {
+            PowerUp::SpeedBoost => Some(PowerUp::SlowMotion),
+            PowerUp::SlowMotion => Some(PowerUp::SpeedBoost),
+            _ => None,
+        };
+        if let Some(opposite) = opposite {
+            if self.is_active(opposite) {
+                self.active.retain(|e| e.kind != opposite);
+                return;
+            }
+        }
+
+        match self.active.iter_mut().find(|e| e.kind == kind) {
+            Some(effect) if kind == PowerUp::ScoreMultiplier => {
+                effect.level = (effect.level+1).min(MAX_MULTIPLIER);
+                effect.remaining = kind.duration();
+            },
+            Some(effect) => {
+                effect.remaining = (effect.remaining+kind.duration()).min(MAX_DURATION_FACTOR*kind.duration());
+            },
+            None => {
+                let level = if kind == PowerUp::ScoreMultiplier { 2 } else { 1 };
+                self.active.push(Effect { kind, remaining: kind.duration(), level });
+            },
+        }
+    }
+
+    // put back an effect exactly as it was, used when loading saved games
+    pub fn restore(&mut self, effect: Effect) {
+        self.active.retain(|e| e.kind != effect.kind);
+        self.active.push(effect);
+    }
+
+    // count down every effect by one tick, dropping any that have run out
+    pub fn tick(&mut self) {
+        self.active.iter_mut().for_each(|e| e.remaining = e.remaining.saturating_sub(1));
+        self.active.retain(|e| e.remaining > 0);
+    }
+
+    pub fn is_active(&self, kind: PowerUp) -> bool {
+        return self.active.iter().any(|e| e.kind == kind);
+    }
+
+    pub fn score_multiplier(&self) -> usize {
+        return self.active.iter().find(|e| e.kind == PowerUp::ScoreMultiplier).map(|e| e.level).unwrap_or(1);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Effect> {
+        return self.active.iter();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn effect_expires() {
+        let mut effects = Effects::default();
+        effects.apply(PowerUp::Ghost);
+        for _ in 0..PowerUp::Ghost.duration()-1 {
+            effects.tick();
+            assert!(effects.is_active(PowerUp::Ghost));
+        }
+        effects.tick();
+        assert!(!effects.is_active(PowerUp::Ghost));
+        assert_eq!(effects.iter().count(), 0);
+    }
+
+    #[test]
+    fn repeat_pickup_extends_up_to_cap() {
+        let mut effects = Effects::default();
+        effects.apply(PowerUp::Magnet);
+        effects.tick();
+        effects.apply(PowerUp::Magnet);
+        let remaining = effects.iter().next().unwrap().remaining;
+        assert_eq!(remaining, 2*PowerUp::Magnet.duration()-1);
+        for _ in 0..5 {
+            effects.apply(PowerUp::Magnet);
+        }
+        let remaining = effects.iter().next().unwrap().remaining;
+        assert_eq!(remaining, MAX_DURATION_FACTOR*PowerUp::Magnet.duration());
+        assert_eq!(effects.iter().count(), 1);
+    }
+
+    #[test]
+    fn speed_and_slow_cancel() {
+        let mut effects = Effects::default();
+        effects.apply(PowerUp::SpeedBoost);
+        effects.apply(PowerUp::SlowMotion);
+        assert!(!effects.is_active(PowerUp::SpeedBoost));
+        assert!(!effects.is_active(PowerUp::SlowMotion));
+        effects.apply(PowerUp::SlowMotion);
+        assert!(effects.is_active(PowerUp::SlowMotion));
+    }
+
+    #[test]
+    fn multiplier_stacks() {
+        let mut effects = Effects::default();
+        assert_eq!(effects.score_multiplier(), 1);
+        effects.apply(PowerUp::ScoreMultiplier);
+        assert_eq!(effects.score_multiplier(), 2);
+        effects.tick();
+        effects.apply(PowerUp::ScoreMultiplier);
+        assert_eq!(effects.score_multiplier(), 3);
+        assert_eq!(effects.iter().next().unwrap().remaining, PowerUp::ScoreMultiplier.duration());
+        effects.apply(PowerUp::ScoreMultiplier);
+        effects.apply(PowerUp::ScoreMultiplier);
+        assert_eq!(effects.score_multiplier(), MAX_MULTIPLIER);
+        for _ in 0..PowerUp::ScoreMultiplier.duration() {
+            effects.tick();
+        }
+        assert_eq!(effects.score_multiplier(), 1);
+    }
+
+    #[test]
+    fn parse_names() {
+        for p in PowerUp::ALL.iter() {
+            assert_eq!(PowerUp::from_str(&p.to_string()), Ok(*p));
+        }
+        assert!(PowerUp::from_str("invisibility").is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use super::GameState;
+use super::mode::{self, GameMode, Classic};
+use super::scoring::ScoreBreakdown;
+use super::sprites::Snake;
+use super::powerup::Effect;
+use super::portal::Portal;
+use super::critter::Critter;
+use super::topology::Topology;
+use super::grid::{self, Grid};
+use super::util::{Direction, Food, FoodGroup, Position, Size};
+
+// Plain text save format, one record per line:
+//
+//   snek-save 1
+//   size <x> <y>
+//   wrap <topology>                       (torus if missing)
+//   grid <cells>                          (square if missing)
+//   wall <x> <y>
+//   score <food> <combo> <early> <length> <survival> <penalties>   (or just <score>)
+//   ticks <ticks>
+//   mode <name>                           (classic if missing)
+//   speed <quarter cells per tick>
+//   snake <dir> <x> <y> [<x> <y> ...]     (head first)
+//   food <group> <x> <y> [<lifetime>]
+//   effect <power-up> <remaining> <level>
+//   portal <x> <y> <x> <y>
+//   critter <kind> <x> <y> <dir>          (or <dx> <dy>, from older saves)
+//   shrunk                                (only if the snake has shrunk)
+const HEADER: &str = "snek-save 1";
+
+fn parse<T: FromStr>(field: Option<&str>, line_no: usize) -> Result<T, String> {
+    let field = field.ok_or_else(|| format!("line {}: missing value", line_no))?;
+    return T::from_str(field).map_err(|_| format!("line {}: bad value '{}'", line_no, field));
+}
+
+// the way a critter from an older save was going, from its step each tick
+fn old_heading(dx: isize, dy: isize) -> Direction {
+    match (dx.signum(), dy.signum()) {
+        (-1, 1)  => Direction::UpLeft,
+        (1, 1)   => Direction::UpRight,
+        (-1, -1) => Direction::DownLeft,
+        (1, -1)  => Direction::DownRight,
+        (-1, _)  => Direction::Left,
+        (1, _)   => Direction::Right,
+        (_, -1)  => Direction::Down,
+        _        => Direction::Up,
+    }
+}
+
+impl GameState {
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), io::Error> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "size {} {}", self.map_size.x, self.map_size.y)?;
+        let topology = &self.topology;
+        writeln!(writer, "wrap {}", topology.wrap())?;
+        writeln!(writer, "grid {}", topology.grid().name())?;
+        for wall in topology.walls() {
+            writeln!(writer, "wall {} {}", wall.x, wall.y)?;
+        }
+        let points = self.scorer.breakdown;
+        writeln!(writer, "score {} {} {} {} {} {}", points.food, points.combo, points.early, points.length, points.survival, points.penalties)?;
+        writeln!(writer, "ticks {}", self.ticks)?;
+        writeln!(writer, "mode {}", self.mode_name())?;
+        writeln!(writer, "speed {}", self.snake.speed())?;
+        write!(writer, "snake {}", self.snake.get_dir())?;
+        for seg in self.snake.iter_segments() {
+            write!(writer, " {} {}", seg.pos.x, seg.pos.y)?;
+        }
+        writeln!(writer)?;
+        for food in self.foods.iter() {
+            write!(writer, "food {} {} {}", food.group, food.pos.x, food.pos.y)?;
+            if let Some(lifetime) = food.lifetime {
+                write!(writer, " {}", lifetime)?;
+            }
+            writeln!(writer)?;
+        }
+        for effect in self.snake.effects().iter() {
+            writeln!(writer, "effect {} {} {}", effect.kind, effect.remaining, effect.level)?;
+        }
+        for portal in self.topology.portals().iter() {
+            writeln!(writer, "portal {} {} {} {}", portal.a.x, portal.a.y, portal.b.x, portal.b.y)?;
+        }
+        for critter in self.critters.iter() {
+            writeln!(writer, "critter {} {} {} {}", critter.kind, critter.pos.x, critter.pos.y, critter.heading)?;
+        }
+        if self.shrunk {
+            writeln!(writer, "shrunk")?;
+        }
+        return Ok(());
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut lines = reader.lines().enumerate();
+        match lines.next() {
+            Some((_, Ok(line))) if line.trim() == HEADER => {},
+            _ => return Err("not a snek save file".to_string()),
+        }
+
+        let mut size: Option<Size> = None;
+        let mut wrap = Default::default();
+        let mut walls: Vec<Position> = Vec::new();
+        let mut cells: &'static dyn Grid = &grid::SQUARE;
+        let mut points = ScoreBreakdown::default();
+        let mut snake: Option<(Direction, Vec<Position>)> = None;
+        let mut foods = Vec::new();
+        let mut effects = Vec::new();
+        let mut portals = Vec::new();
+        let mut critters = Vec::new();
+        let mut shrunk = false;
+        let mut ticks = 0;
+        let mut mode: Box<dyn GameMode> = Box::new(Classic);
+        let mut speed = None;
+        for (index, line) in lines {
+            let line_no = index+1;
+            let line = line.map_err(|e| e.to_string())?;
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("size") => {
+                    size = Some([parse(fields.next(), line_no)?, parse(fields.next(), line_no)?].into());
+                },
+                Some("wrap") => {
+                    wrap = parse(fields.next(), line_no)?;
+                },
+                Some("grid") => {
+                    cells = grid::from_name(fields.next().unwrap_or(""))
+                        .map_err(|e| format!("line {}: {}", line_no, e))?;
+                },
+                Some("wall") => {
+                    walls.push([parse(fields.next(), line_no)?, parse(fields.next(), line_no)?].into());
+                },
+                Some("score") => {
+                    let values = fields.map(|f| parse(Some(f), line_no)).collect::<Result<Vec<usize>,_>>()?;
+                    points = match values.as_slice() {
+                        // older saves only kept the total
+                        [total] => ScoreBreakdown { food: *total, ..Default::default() },
+                        [food, combo, early, length, penalties] => ScoreBreakdown {
+                            food: *food, combo: *combo, early: *early, length: *length, survival: 0, penalties: *penalties,
+                        },
+                        [food, combo, early, length, survival, penalties] => ScoreBreakdown {
+                            food: *food, combo: *combo, early: *early, length: *length, survival: *survival, penalties: *penalties,
+                        },
+                        _ => return Err(format!("line {}: bad score", line_no)),
+                    };
+                },
+                Some("snake") => {
+                    let dir = parse(fields.next(), line_no)?;
+                    let coords = fields.map(|f| parse(Some(f), line_no)).collect::<Result<Vec<usize>,_>>()?;
+                    if coords.is_empty() || coords.len()%2 != 0 {
+                        return Err(format!("line {}: bad snake segments", line_no));
+                    }
+                    let positions = coords.chunks(2).map(|c| [c[0],c[1]].into()).collect();
+                    snake = Some((dir, positions));
+                },
+                Some("food") => {
+                    let group: FoodGroup = parse(fields.next(), line_no)?;
+                    let pos: Position = [parse(fields.next(), line_no)?, parse(fields.next(), line_no)?].into();
+                    let food = match fields.next() {
+                        Some(lifetime) => Food::new_with_lifetime(pos, group, parse(Some(lifetime), line_no)?),
+                        None           => Food::new(pos, group),
+                    };
+                    foods.push(food);
+                },
+                Some("effect") => {
+                    effects.push(Effect {
+                        kind: parse(fields.next(), line_no)?,
+                        remaining: parse(fields.next(), line_no)?,
+                        level: parse(fields.next(), line_no)?,
+                    });
+                },
+                Some("portal") => {
+                    let a = [parse(fields.next(), line_no)?, parse(fields.next(), line_no)?];
+                    let b = [parse(fields.next(), line_no)?, parse(fields.next(), line_no)?];
+                    portals.push(Portal::new(a, b));
+                },
+                Some("critter") => {
+                    let kind = parse(fields.next(), line_no)?;
+                    let pos = [parse(fields.next(), line_no)?, parse(fields.next(), line_no)?];
+                    let rest: Vec<&str> = fields.collect();
+                    let heading = match rest.as_slice() {
+                        [dir] => parse(Some(dir), line_no)?,
+                        // older saves kept the step taken each tick
+                        [dx, dy] => old_heading(parse(Some(dx), line_no)?, parse(Some(dy), line_no)?),
+                        _ => return Err(format!("line {}: bad critter heading", line_no)),
+                    };
+                    critters.push(Critter::new(kind, pos, heading));
+                },
+                Some("speed") => {
+                    speed = Some(parse(fields.next(), line_no)?);
+                },
+                Some("ticks") => {
+                    ticks = parse(fields.next(), line_no)?;
+                },
+                Some("mode") => {
+                    mode = mode::from_name(fields.next().unwrap_or(""))
+                        .map_err(|e| format!("line {}: {}", line_no, e))?;
+                },
+                Some("shrunk") => {
+                    shrunk = true;
+                },
+                None => {},
+                Some(other) => return Err(format!("line {}: unknown record '{}'", line_no, other)),
+            }
+        }
+
+        let size = size.ok_or("save file has no size")?;
+        let (dir, positions) = snake.ok_or("save file has no snake")?;
+        let mut topology = Topology::new(wrap, size);
+        topology.set_grid(cells);
+        topology.check()?;
+        walls.into_iter().for_each(|w| topology.add_wall(w));
+        portals.into_iter().for_each(|p| topology.add_portal(p));
+        let on_map = |p: &Position| topology.contains(*p);
+        if !positions.iter().all(on_map) || !foods.iter().all(|f| on_map(&f.pos))
+            || !topology.portals().iter().all(|p| on_map(&p.a) && on_map(&p.b))
+            || !critters.iter().all(|c| on_map(&c.pos)) {
+            return Err("save file has items outside the map".to_string());
+        }
+
+        let mut gs = GameState::new(size, positions[0]);
+        gs.set_topology(topology);
+        gs.snake = Snake::from_segments(positions, dir, size);
+        if let Some(speed) = speed {
+            gs.snake.set_speed(speed);
+        }
+        effects.into_iter().for_each(|e| gs.snake.effects_mut().restore(e));
+        foods.into_iter().for_each(|f| { gs.foods.push(f); });
+        gs.critters = critters;
+        gs.scorer.breakdown = points;
+        gs.shrunk = shrunk;
+        gs.ticks = ticks;
+        gs.set_mode(mode);
+        return Ok(gs);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::powerup::PowerUp;
+    use crate::game::critter::CritterKind;
+    use crate::game::topology::Wrap;
+
+    #[test]
+    fn save_and_load() {
+        let mut gs = GameState::new([6,5],[2,2]);
+        let mut topology = Topology::new(Wrap::Klein, gs.map_size);
+        topology.add_wall([5,2].into());
+        gs.set_topology(topology);
+        gs.snake = Snake::from_segments(vec![[2,2].into(),[2,1].into(),[3,1].into()], Direction::Left, gs.map_size);
+        gs.foods.push(Food::new([0,4], FoodGroup::Grow));
+        gs.foods.push(Food::new_with_lifetime([5,0], FoodGroup::Poison, 3));
+        gs.foods.push(Food::new_with_lifetime([1,1], FoodGroup::PowerUp(PowerUp::Magnet), 6));
+        gs.snake.effects_mut().apply(PowerUp::ScoreMultiplier);
+        assert!(gs.add_portal([0,0],[5,4]));
+        gs.critters.push(Critter::new(CritterKind::Bouncer, [4,3], Direction::UpLeft));
+        gs.scorer.breakdown = ScoreBreakdown { food: 10, combo: 3, early: 1, length: 0, survival: 4, penalties: 6 };
+        gs.ticks = 40;
+        gs.set_mode(mode::from_name("time-attack:240").unwrap());
+        gs.snake.set_speed(6);
+        gs.shrunk = true;
+
+        let mut out = Vec::new();
+        gs.save(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "snek-save 1\nsize 6 5\nwrap klein\ngrid square\nwall 5 2\nscore 10 3 1 0 4 6\nticks 40\nmode time-attack:240\nspeed 6\nsnake left 2 2 2 1 3 1\nfood grow 0 4\nfood poison 5 0 3\nfood magnet 1 1 6\neffect multiplier 30 2\nportal 0 0 5 4\ncritter bouncer 4 3 up-left\nshrunk\n");
+
+        let loaded = GameState::load(text.as_bytes()).unwrap();
+        assert_eq!(loaded.map_size, gs.map_size);
+        assert_eq!(loaded.topology, gs.topology);
+        assert_eq!(loaded.score(), 12);
+        assert_eq!(loaded.score_breakdown(), gs.score_breakdown());
+        assert_eq!(loaded.foods, gs.foods);
+        assert_eq!(loaded.snake.get_dir(), Direction::Left);
+        assert_eq!(loaded.snake.effects(), gs.snake.effects());
+        assert_eq!(loaded.critters, gs.critters);
+        assert!(loaded.shrunk);
+        assert_eq!(loaded.ticks, 40);
+        assert_eq!(loaded.mode_name(), "time-attack:240");
+        assert_eq!(loaded.get_render_map().status, Some("time:200".to_string()));
+        assert_eq!(loaded.snake.speed(), 6);
+        assert!(loaded.snake.iter_segments().eq(gs.snake.iter_segments()));
+    }
+
+    #[test]
+    fn load_old_score() {
+        let gs = GameState::load("snek-save 1\nsize 5 5\nscore 7\nsnake up 1 1\n".as_bytes()).unwrap();
+        assert_eq!(gs.score(), 7);
+        assert_eq!(gs.mode_name(), "classic");
+    }
+
+    #[test]
+    fn load_old_critters() {
+        let gs = GameState::load("snek-save 1\nsize 5 5\nsnake up 1 1\ncritter patrol 3 3 0 -1\ncritter mouse 2 2 0 0\n".as_bytes()).unwrap();
+        assert_eq!(gs.critters[0].heading, Direction::Down);
+        assert_eq!(gs.critters[1].heading, Direction::Up);
+        assert!(GameState::load("snek-save 1\nsize 5 5\nsnake up 1 1\ncritter patrol 3 3\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn load_hex_game() {
+        let gs = GameState::load("snek-save 1\nsize 5 5\ngrid hex\nsnake down-left 1 1 1 2\n".as_bytes()).unwrap();
+        assert_eq!(gs.topology.grid().shape(), grid::Shape::Hex);
+        assert_eq!(gs.snake.get_dir(), Direction::DownLeft);
+    }
+
+    #[test]
+    fn load_bad_files() {
+        assert!(GameState::load("hello".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nscore 1 2\nsnake up 1 1\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nsnake up 9 9\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nsnake up 1\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nsnake up 1 1\nfood cake 1 2\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nsnake up 1 1\nportal 0 0 5 5\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nwall 1 1\nsnake up 1 1\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\ngrid triangle\nsnake up 1 1\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nwrap sphere\nsnake up 1 1\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nwrap mobius\ngrid hex\nsnake up 1 1\n".as_bytes()).is_err());
+        assert!(GameState::load("snek-save 1\nsize 5 5\nmode tetris\nsnake up 1 1\n".as_bytes()).is_err());
+    }
+}
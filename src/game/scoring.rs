@@ -0,0 +1,216 @@
+use std::str::FromStr;
+
+use super::util::FoodGroup;
+
+// How many points things are worth. Negative values are penalties.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScoringRules {
+    pub grow: isize,
+    pub shrink: isize,
+    pub poison: isize,
+    pub powerup: isize,
+    pub mouse: isize,
+    // meals no more than this many ticks apart build up a combo
+    pub combo_window: usize,
+    // the combo stops growing at this many meals
+    pub max_combo: usize,
+    // one bonus point for every this many ticks left on food that rots
+    pub early_ticks: usize,
+    // the snake's points go up by one lot for every this many segments
+    pub length_step: usize,
+    // lost for each segment bitten off under the self-cut rule
+    pub cut: usize,
+    // lost every tick the snake is boosted above its normal speed
+    pub boost: usize,
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self {
+            grow: 1,
+            shrink: -1,
+            poison: 0,
+            powerup: 1,
+            mouse: 5,
+            combo_window: 3,
+            max_combo: 4,
+            early_ticks: 5,
+            length_step: 10,
+            cut: 1,
+            boost: 1,
+        }
+    }
+}
+
+impl ScoringRules {
+    pub const KEYS: [&'static str; 11] = [
+        "grow", "shrink", "poison", "powerup", "mouse",
+        "combo-window", "max-combo", "early-ticks", "length-step", "cut", "boost",
+    ];
+
+    // set one rule by name, as found in the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let bad = |_| format!("invalid value '{}' for score rule '{}'", value, key);
+        match key {
+            "grow"         => self.grow = isize::from_str(value).map_err(bad)?,
+            "shrink"       => self.shrink = isize::from_str(value).map_err(bad)?,
+            "poison"       => self.poison = isize::from_str(value).map_err(bad)?,
+            "powerup"      => self.powerup = isize::from_str(value).map_err(bad)?,
+            "mouse"        => self.mouse = isize::from_str(value).map_err(bad)?,
+            "combo-window" => self.combo_window = usize::from_str(value).map_err(bad)?,
+            "max-combo"    => self.max_combo = usize::from_str(value).map_err(bad)?.max(1),
+            "early-ticks"  => self.early_ticks = usize::from_str(value).map_err(bad)?,
+            "length-step"  => self.length_step = usize::from_str(value).map_err(bad)?,
+            "cut"          => self.cut = usize::from_str(value).map_err(bad)?,
+            "boost"        => self.boost = usize::from_str(value).map_err(bad)?,
+            _ => return Err(format!("unknown score rule '{}', expected one of: {}", key, Self::KEYS.join(", "))),
+        }
+        return Ok(());
+    }
+
+    pub fn value(&self, group: FoodGroup) -> isize {
+        match group {
+            FoodGroup::Grow       => self.grow,
+            FoodGroup::Shrink     => self.shrink,
+            FoodGroup::Poison     => self.poison,
+            FoodGroup::PowerUp(_) => self.powerup,
+        }
+    }
+}
+
+// Where the points came from
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ScoreBreakdown {
+    pub food: usize,
+    pub combo: usize,
+    pub early: usize,
+    pub length: usize,
+    // for staying alive, in modes that score it
+    pub survival: usize,
+    pub penalties: usize,
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> usize {
+        return (self.food+self.combo+self.early+self.length+self.survival).saturating_sub(self.penalties);
+    }
+}
+
+// Something the snake ate, and the state it was in at the time
+pub struct Meal {
+    pub value: isize,
+    pub tick: usize,
+    // ticks left before the food would have rotted
+    pub lifetime: Option<usize>,
+    pub length: usize,
+    // from the score multiplier power-up
+    pub multiplier: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Scorer {
+    pub rules: ScoringRules,
+    pub breakdown: ScoreBreakdown,
+    combo: usize,
+    last_meal: Option<usize>,
+}
+
+impl Scorer {
+    pub fn total(&self) -> usize {
+        return self.breakdown.total();
+    }
+
+    // meals in a row so far, 0 if the combo has lapsed
+    pub fn combo(&self) -> usize {
+        return self.combo;
+    }
+
+    pub fn survive(&mut self, points: usize) {
+        self.breakdown.survival += points;
+    }
+
+    pub fn penalise(&mut self, points: usize) {
+        self.breakdown.penalties += points;
+    }
+
+    pub fn eat(&mut self, meal: Meal) {
+        if meal.value <= 0 {
+            // penalties break the combo too
+            self.breakdown.penalties += meal.value.unsigned_abs();
+            self.combo = 0;
+            self.last_meal = None;
+            return;
+        }
+        let quick = self.last_meal.is_some_and(|last| meal.tick-last <= self.rules.combo_window);
+        self.combo = if quick { (self.combo+1).min(self.rules.max_combo) } else { 1 };
+        self.last_meal = Some(meal.tick);
+
+        let points = meal.value.unsigned_abs()*meal.multiplier;
+        self.breakdown.food += points;
+        self.breakdown.combo += points*(self.combo-1);
+        // a zero in either rule turns that bonus off
+        self.breakdown.early += meal.lifetime.unwrap_or(0).checked_div(self.rules.early_ticks).unwrap_or(0);
+        self.breakdown.length += points*meal.length.checked_div(self.rules.length_step).unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn meal(value: isize, tick: usize) -> Meal {
+        Meal { value, tick, lifetime: None, length: 1, multiplier: 1 }
+    }
+
+    #[test]
+    fn combos_build_and_lapse() {
+        let mut scorer = Scorer::default();
+        scorer.eat(meal(1, 0));
+        scorer.eat(meal(1, 2));
+        scorer.eat(meal(1, 5));
+        assert_eq!(scorer.combo(), 3);
+        assert_eq!(scorer.breakdown, ScoreBreakdown { food: 3, combo: 3, ..Default::default() });
+        // too slow, start again
+        scorer.eat(meal(1, 20));
+        assert_eq!(scorer.combo(), 1);
+        assert_eq!(scorer.total(), 7);
+    }
+
+    #[test]
+    fn combo_is_capped() {
+        let mut scorer = Scorer::default();
+        for tick in 0..10 {
+            scorer.eat(meal(1, tick));
+        }
+        assert_eq!(scorer.combo(), scorer.rules.max_combo);
+    }
+
+    #[test]
+    fn bonuses_and_penalties() {
+        let mut scorer = Scorer::default();
+        scorer.eat(Meal { value: 2, tick: 0, lifetime: Some(11), length: 23, multiplier: 2 });
+        assert_eq!(scorer.breakdown, ScoreBreakdown { food: 4, early: 2, length: 8, ..Default::default() });
+
+        scorer.eat(meal(-3, 1));
+        assert_eq!(scorer.breakdown.penalties, 3);
+        assert_eq!(scorer.combo(), 0);
+        assert_eq!(scorer.total(), 11);
+
+        // never goes below zero
+        scorer.eat(meal(-50, 2));
+        assert_eq!(scorer.total(), 0);
+    }
+
+    #[test]
+    fn set_rules() {
+        let mut rules = ScoringRules::default();
+        rules.set("grow", "3").unwrap();
+        rules.set("shrink", "-4").unwrap();
+        rules.set("combo-window", "0").unwrap();
+        assert_eq!(rules.value(FoodGroup::Grow), 3);
+        assert_eq!(rules.value(FoodGroup::Shrink), -4);
+        assert_eq!(rules.combo_window, 0);
+        assert!(rules.set("cake", "1").is_err());
+        assert!(rules.set("max-combo", "-1").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+use rand::Rng;
+
+use super::util::{Position, Size};
+
+// Where new food is put, chosen from the cells with nothing in them
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SpawnStrategy {
+    // any free cell is as likely as any other
+    #[default]
+    Uniform,
+    // at least this many steps from the snake's head
+    AwayFromHead(usize),
+    // within this many steps of food already on the board
+    Clustered(usize),
+    // more likely the nearer a cell is to the edge
+    EdgeBiased,
+}
+
+impl fmt::Display for SpawnStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnStrategy::Uniform         => write!(f, "uniform"),
+            SpawnStrategy::AwayFromHead(d) => write!(f, "away:{}", d),
+            SpawnStrategy::Clustered(r)    => write!(f, "clustered:{}", r),
+            SpawnStrategy::EdgeBiased      => write!(f, "edge"),
+        }
+    }
+}
+
+// "uniform", "away[:N]", "clustered[:N]" or "edge"
+impl FromStr for SpawnStrategy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let arg = match parts.next() {
+            Some(n) => Some(usize::from_str(n).map_err(|_| format!("bad number in spawn strategy '{}'", s))?),
+            None    => None,
+        };
+        match name {
+            "uniform"   => Ok(SpawnStrategy::Uniform),
+            "away"      => Ok(SpawnStrategy::AwayFromHead(arg.unwrap_or(4))),
+            "clustered" => Ok(SpawnStrategy::Clustered(arg.unwrap_or(2))),
+            "edge"      => Ok(SpawnStrategy::EdgeBiased),
+            _ => Err(format!("unknown spawn strategy '{}', expected uniform, away[:N], clustered[:N] or edge", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SpawnRules {
+    pub strategy: SpawnStrategy,
+    // no more food is added while this many are on the board
+    pub max_food: Option<usize>,
+}
+
+// steps between two cells on a map that wraps round at the edges
+pub fn wrapped_distance(a: Position, b: Position, size: Size) -> usize {
+    let dx = a.x.abs_diff(b.x);
+    let dy = a.y.abs_diff(b.y);
+    return dx.min(size.x-dx) + dy.min(size.y-dy);
+}
+
+fn edge_distance(pos: Position, size: Size) -> usize {
+    return pos.x.min(size.x-1-pos.x).min(pos.y).min(size.y-1-pos.y);
+}
+
+impl SpawnStrategy {
+    // Pick a cell for new food out of `free`. Returns None only if there
+    // are no free cells at all.
+    pub fn pick<R: Rng>(&self, free: &[Position], head: Position, foods: &[Position], size: Size, rng: &mut R) -> Option<Position> {
+        if free.is_empty() {
+            return None;
+        }
+        let candidates: Vec<Position> = match self {
+            SpawnStrategy::Uniform | SpawnStrategy::EdgeBiased => free.to_vec(),
+            SpawnStrategy::AwayFromHead(min) => {
+                free.iter().copied().filter(|p| wrapped_distance(*p, head, size) >= *min).collect()
+            },
+            SpawnStrategy::Clustered(radius) => {
+                free.iter().copied().filter(|p| foods.iter().any(|f| wrapped_distance(*p, *f, size) <= *radius)).collect()
+            },
+        };
+        // fall back to anywhere free if the strategy rules everything out
+        let candidates = if candidates.is_empty() { free.to_vec() } else { candidates };
+
+        if let SpawnStrategy::EdgeBiased = self {
+            let deepest = candidates.iter().map(|p| edge_distance(*p, size)).max().unwrap();
+            let weight = |p: &Position| deepest+1-edge_distance(*p, size);
+            let total: usize = candidates.iter().map(weight).sum();
+            let mut roll = rng.gen_range(0..total);
+            for p in candidates.iter() {
+                if roll < weight(p) {
+                    return Some(*p);
+                }
+                roll -= weight(p);
+            }
+            unreachable!();
+        }
+        return Some(candidates[rng.gen_range(0..candidates.len())]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const SIZE: Size = Size { x: 7, y: 7 };
+
+    fn all_cells() -> Vec<Position> {
+        (0..SIZE.x).flat_map(|x| (0..SIZE.y).map(move |y| [x,y].into())).collect()
+    }
+
+    #[test]
+    fn parse_strategies() {
+        assert_eq!(SpawnStrategy::from_str("uniform"), Ok(SpawnStrategy::Uniform));
+        assert_eq!(SpawnStrategy::from_str("away:6"), Ok(SpawnStrategy::AwayFromHead(6)));
+        assert_eq!(SpawnStrategy::from_str("clustered"), Ok(SpawnStrategy::Clustered(2)));
+        assert_eq!(SpawnStrategy::from_str("edge"), Ok(SpawnStrategy::EdgeBiased));
+        assert!(SpawnStrategy::from_str("away:x").is_err());
+        assert!(SpawnStrategy::from_str("middle").is_err());
+        for s in ["uniform", "away:3", "clustered:1", "edge"].iter() {
+            assert_eq!(SpawnStrategy::from_str(s).unwrap().to_string(), *s);
+        }
+    }
+
+    #[test]
+    fn only_free_cells() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let free = vec![[2,3].into()];
+        for strategy in [SpawnStrategy::Uniform, SpawnStrategy::AwayFromHead(5), SpawnStrategy::Clustered(1), SpawnStrategy::EdgeBiased].iter() {
+            assert_eq!(strategy.pick(&free, [2,2].into(), &[], SIZE, &mut rng), Some([2,3].into()));
+            assert_eq!(strategy.pick(&[], [2,2].into(), &[], SIZE, &mut rng), None);
+        }
+    }
+
+    #[test]
+    fn away_from_head() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let head: Position = [3,3].into();
+        for _ in 0..50 {
+            let p = SpawnStrategy::AwayFromHead(4).pick(&all_cells(), head, &[], SIZE, &mut rng).unwrap();
+            assert!(wrapped_distance(p, head, SIZE) >= 4);
+        }
+    }
+
+    #[test]
+    fn clustered_near_food() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let food: Position = [0,0].into();
+        for _ in 0..50 {
+            let p = SpawnStrategy::Clustered(1).pick(&all_cells(), [3,3].into(), &[food], SIZE, &mut rng).unwrap();
+            assert!(wrapped_distance(p, food, SIZE) <= 1);
+        }
+    }
+
+    #[test]
+    fn edge_biased_prefers_edges() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let picks: Vec<Position> = (0..400).map(|_| {
+            SpawnStrategy::EdgeBiased.pick(&all_cells(), [3,3].into(), &[], SIZE, &mut rng).unwrap()
+        }).collect();
+        let on_edge = picks.iter().filter(|p| edge_distance(**p, SIZE) == 0).count();
+        let centre = picks.iter().filter(|p| edge_distance(**p, SIZE) == 3).count();
+        // the 24 edge cells carry 96 of the 161 total weight, the centre 1
+        assert!(on_edge > 200);
+        assert!(centre < 10);
+    }
+
+    #[test]
+    fn distance_wraps() {
+        assert_eq!(wrapped_distance([0,0].into(), [6,6].into(), SIZE), 2);
+        assert_eq!(wrapped_distance([1,1].into(), [3,2].into(), SIZE), 3);
+    }
+}
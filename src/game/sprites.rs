@@ -1,14 +1,33 @@
 use std::collections::{VecDeque,vec_deque::Iter};
+use std::fmt;
+use std::str::FromStr;
 use super::util::{Position, Direction, Size, FoodGroup};
+use super::powerup::{PowerUp, Effects};
+use super::topology::Topology;
+use super::occupancy::Occupancy;
 
-// Snake
+// Snake. The board it's on belongs to the game, which hands it over
+// whenever the snake needs to know where a step takes it.
 pub struct Snake {
     segments: VecDeque<Segment>,
+    // how many segments are on each cell, kept along with `segments`
+    occupied: Occupancy<usize>,
     travel_dir: Direction,
     travelled_dir: Direction,
-    map_size: Size,
+    effects: Effects,
+    collision: CollisionPolicy,
+    // in quarter cells per tick, see SPEED_UNIT
+    speed: usize,
+    // quarter cells gone towards the next step
+    progress: usize,
 }
 
+// Speeds are counted in fractions of a cell per tick, so snakes can move at
+// rates in between one cell a tick and two.
+pub const SPEED_UNIT: usize = 4;
+pub const MIN_SPEED: usize = 1;
+pub const MAX_SPEED: usize = 3*SPEED_UNIT;
+
 #[derive(Clone,Copy,Debug,PartialEq)]
 pub struct Segment {
     pub pos: Position,
@@ -37,78 +56,193 @@ impl Snake {
         // segments.push_front(Segment::new(pos));
         segments.push_back(Segment::new(pos));
         // println!("segments:\n{:?}",segments);
+        let occupied = occupancy(&segments, map_size);
         Self {
             segments,
+            occupied,
             travel_dir: Direction::Up,
             travelled_dir: Direction::Up,
-            map_size,
+            effects: Effects::default(),
+            collision: CollisionPolicy::default(),
+            speed: SPEED_UNIT,
+            progress: 0,
+        }
+    }
+
+    // rebuild a snake from its segment positions, head first
+    pub fn from_segments(positions: Vec<Position>, dir: Direction, map_size: Size) -> Self {
+        let segments = positions.into_iter().map(Segment::new).collect();
+        let occupied = occupancy(&segments, map_size);
+        Self {
+            segments,
+            occupied,
+            travel_dir: dir,
+            travelled_dir: dir,
+            effects: Effects::default(),
+            collision: CollisionPolicy::default(),
+            speed: SPEED_UNIT,
+            progress: 0,
         }
     }
 
-    pub fn set_dir(&mut self, dir: Direction) {
-        // this is to stop the player going back into themselves immediately
-        if dir != self.travelled_dir.opposite() {
+    pub fn effects(&self) -> &Effects {
+        return &self.effects;
+    }
+
+    pub fn effects_mut(&mut self) -> &mut Effects {
+        return &mut self.effects;
+    }
+
+    pub fn collision(&self) -> CollisionPolicy {
+        return self.collision;
+    }
+
+    pub fn set_collision(&mut self, policy: CollisionPolicy) {
+        self.collision = policy;
+    }
+
+    pub fn speed(&self) -> usize {
+        return self.speed;
+    }
+
+    pub fn set_speed(&mut self, speed: usize) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn accelerate(&mut self) {
+        self.set_speed(self.speed+1);
+    }
+
+    pub fn brake(&mut self) {
+        self.set_speed(self.speed-1);
+    }
+
+    // How many cells to move this tick. Speed power-ups double or halve the
+    // snake's own speed.
+    pub fn steps_this_tick(&mut self) -> usize {
+        let mut speed = self.speed;
+        if self.effects.is_active(PowerUp::SpeedBoost) {
+            speed *= 2;
+        } else if self.effects.is_active(PowerUp::SlowMotion) {
+            speed = (speed/2).max(MIN_SPEED);
+        }
+        self.progress += speed;
+        let steps = self.progress/SPEED_UNIT;
+        self.progress %= SPEED_UNIT;
+        return steps;
+    }
+
+    // Get ready for a new board. Its grid may not have the way the snake
+    // was heading, in which case it sets off the first way the grid has.
+    pub fn fit_topology(&mut self, topology: &Topology) {
+        let directions = topology.grid().directions();
+        if !directions.contains(&self.travel_dir) {
+            self.travel_dir = directions[0];
+            self.travelled_dir = directions[0];
+        }
+        if topology.size() != self.occupied.size() {
+            self.occupied = occupancy(&self.segments, topology.size());
+        }
+    }
+
+    pub fn head_pos(&self) -> Position {
+        return self.segments.front().unwrap().pos;
+    }
+
+
+    pub fn get_dir(&self) -> Direction {
+        return self.travelled_dir;
+    }
+
+    pub fn set_dir(&mut self, dir: Direction, topology: &Topology) {
+        // this is to stop the player going back into themselves immediately,
+        // or heading a way there isn't on this grid
+        if dir != self.travelled_dir.opposite() && topology.grid().directions().contains(&dir) {
             self.travel_dir = dir;
         }
     }
 
-    pub fn get_next_head_pos(&mut self) -> Position {
-        // println!("--> get_next_head_pos");
-        // println!("\t 0.self.segments: {:?}",self.segments);
-        let (x,y) = self.segments.get(0).unwrap().pos.into();
-
-        let next_pos: Position = match self.travel_dir {
-            // this handles mapping onto the opposite side of the map
-            Direction::Up if y == (self.map_size.y-1) => {
-                [x,0].into()
-            },
-            Direction::Down if y == 0 => {
-                [x,self.map_size.y-1].into()
-            },
-            Direction::Right if x == (self.map_size.x-1) => {
-                [0,y].into()
-            },
-            Direction::Left if x == 0 => {
-                [self.map_size.x-1,y].into()
-            },
-            // this handles the snake moving normally
-            _ => {
-                self.segments.get(0).unwrap().pos+self.travel_dir
-            }
-        };
-        // println!("\t 1.self.segments: {:?}",self.segments);
-        return next_pos;
+    // Head a notch clockwise or anticlockwise of the way the snake last
+    // moved. Turning twice before moving still only turns once.
+    pub fn turn(&mut self, clockwise: bool, topology: &Topology) {
+        let directions = topology.grid().directions();
+        let count = directions.len();
+        let index = directions.iter().position(|d| *d == self.travelled_dir).unwrap_or(0);
+        let next = if clockwise { (index+1)%count } else { (index+count-1)%count };
+        self.travel_dir = directions[next];
+    }
+
+    // None if the snake is about to run into a wall
+    pub fn get_next_head_pos(&self, topology: &Topology) -> Option<Position> {
+        let next_pos = topology.step(self.head_pos(), self.travel_dir)?;
+        // stepping onto a portal brings the head out of its twin
+        return Some(topology.warp(next_pos));
     }
 
-    pub fn next_head_pos_in_body(&mut self) -> bool {
+    pub fn next_head_pos_in_body(&self, topology: &Topology) -> bool {
         // println!("--> next_head_pos_in_body");
-        let next_head_pos = self.get_next_head_pos();
-        return self.is_in_snake(next_head_pos);
+        return self.get_next_head_pos(topology).is_some_and(|pos| self.is_in_snake(pos));
     }
 
-    pub fn is_in_snake<T: Into<Position>>(&mut self, pos: T) -> bool {
-        let local_pos : Position = pos.into();
-        for seg in self.segments.iter() {
-            if local_pos==seg.pos {
-                return true;
-            }
+    // whether the next move, eating `food`, runs the head into the body
+    pub fn bites_itself(&self, food: Option<FoodGroup>, topology: &Topology) -> bool {
+        // ghosts pass straight through themselves
+        if self.effects.is_active(PowerUp::Ghost) || !self.next_head_pos_in_body(topology) {
+            return false;
+        }
+        // unless the snake is growing, the tail moves out of the way in time
+        let tail = self.segments.back().unwrap().pos;
+        let growing = food == Some(FoodGroup::Grow);
+        if self.collision != CollisionPolicy::Strict && !growing && self.get_next_head_pos(topology) == Some(tail) {
+            return false;
         }
-        return false;
+        return true;
+    }
+
+    pub fn is_in_snake<T: Into<Position>>(&self, pos: T) -> bool {
+        return self.occupied.contains(pos.into());
     }
 
     fn add_front_segment<T: Into<Segment>>(&mut self, seg: T) {
-        self.segments.push_front(seg.into());
+        let seg = seg.into();
+        self.occupied.add(seg.pos);
+        self.segments.push_front(seg);
+    }
+
+    fn pop_tail(&mut self) {
+        if let Some(seg) = self.segments.pop_back() {
+            self.occupied.remove(seg.pos);
+        }
+    }
+
+    // Cut off the segment at `pos` and everything behind it, returning how
+    // many segments were lost.
+    pub fn trim_from(&mut self, pos: Position) -> usize {
+        let before = self.segments.len();
+        if !self.is_in_snake(pos) {
+            return 0;
+        }
+        if let Some(index) = self.segments.iter().position(|seg| seg.pos == pos) {
+            while self.segments.len() > index.max(1) {
+                self.pop_tail();
+            }
+        }
+        return before-self.segments.len();
     }
 
     fn drop_last_segment(&mut self) {
-        if self.segments.make_contiguous().len() > 1 {
-            self.segments.pop_back();
+        if self.segments.len() > 1 {
+            self.pop_tail();
         }
     }
 
     // use the current travel direction to update the snake position based on the direction.
-    pub fn slither(&mut self, food_to_eat: Option<FoodGroup>) -> SnakeState {
-        if self.next_head_pos_in_body() {
+    pub fn slither(&mut self, food_to_eat: Option<FoodGroup>, topology: &Topology) -> SnakeState {
+        let next_pos = match self.get_next_head_pos(topology) {
+            Some(pos) => pos,
+            None      => return SnakeState::Dead(Reason::Wall),
+        };
+        if self.bites_itself(food_to_eat, topology) {
             return SnakeState::Dead(Reason::Collision);
         }
         // println!("\t 1.self.segments: {:?}",self.segments);
@@ -116,8 +250,6 @@ impl Snake {
             match food {
                 FoodGroup::Grow => {
                     // println!("\t 2.self.segments: {:?}",self.segments);
-                    let next_pos = self.get_next_head_pos();
-                    // println!("\t 3.self.segments: {:?}",self.segments);
                     let seg = Segment::new(next_pos);
                     self.add_front_segment(seg);
                     // println!("\t 4.self.segments: {:?}",self.segments);
@@ -128,14 +260,17 @@ impl Snake {
                 FoodGroup::Shrink => {
                     self.drop_last_segment();
                     self.drop_last_segment();
-                    let next_pos = self.get_next_head_pos();
                     let seg = Segment::new(next_pos);
                     self.add_front_segment(seg);
                 }
+                FoodGroup::PowerUp(kind) => {
+                    self.pop_tail();
+                    self.add_front_segment(Segment::new(next_pos));
+                    self.effects.apply(kind);
+                }
             }
         } else {
-            let next_pos = self.get_next_head_pos();
-            self.drop_last_segment();
+            self.pop_tail();
             let seg = Segment::new(next_pos);
             self.add_front_segment(seg);
         }
@@ -143,27 +278,95 @@ impl Snake {
         return SnakeState::Alive;
     }
 
-    pub fn iter_segments(&self) -> Iter<Segment> {
+    pub fn iter_segments(&self) -> Iter<'_, Segment> {
         return self.segments.iter();
     }
 }
 
+// count up the segments on each cell
+fn occupancy(segments: &VecDeque<Segment>, size: Size) -> Occupancy<usize> {
+    let mut occupied = Occupancy::new(size);
+    segments.iter().for_each(|seg| occupied.add(seg.pos));
+    return occupied;
+}
+
+
+// What happens when the head runs into the body
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CollisionPolicy {
+    // the tail cell is safe, as the tail moves out of it
+    Classic,
+    // any part of the body is deadly, even the tail, as it always has been
+    #[default]
+    Strict,
+    // biting the body cuts it off there for a score penalty, rather than
+    // dying; GameState does the cutting before the snake moves
+    SelfCut,
+}
+
+impl CollisionPolicy {
+    pub const NAMES: [&'static str; 3] = ["classic", "strict", "self-cut"];
+}
+
+impl fmt::Display for CollisionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CollisionPolicy::Classic => "classic",
+            CollisionPolicy::Strict  => "strict",
+            CollisionPolicy::SelfCut => "self-cut",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic"  => Ok(CollisionPolicy::Classic),
+            "strict"   => Ok(CollisionPolicy::Strict),
+            "self-cut" => Ok(CollisionPolicy::SelfCut),
+            _ => Err(format!("unknown collision rule '{}', expected one of: {}", s, Self::NAMES.join(", "))),
+        }
+    }
+}
 
 #[derive(PartialEq, Debug)]
 pub enum SnakeState {
     Alive,
     Dead(Reason),
+    // filled the board or grew to the target length
+    Won,
+    // ran out of time in a timed game
+    TimeUp,
 }
 
 #[derive(PartialEq, Debug)]
 pub enum Reason {
     Collision,
     Poison,
+    Hazard,
+    // ran off the edge of the board or into a wall
+    Wall,
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use super::super::topology::Wrap;
+    use super::super::portal::Portal;
+
+    // the board most tests are played on
+    fn board() -> Topology {
+        return Topology::new(Wrap::Torus, Size{x:5,y:5});
+    }
+
+    fn board_with_portal(a: [usize;2], b: [usize;2]) -> Topology {
+        let mut topology = board();
+        topology.add_portal(Portal::new(a, b));
+        return topology;
+    }
+
     #[test]
     fn init_snake() {
         let s = Snake::new([2,2], Size{x:5,y:5});
@@ -171,7 +374,6 @@ mod test {
         assert_eq!(s.segments.len(),1);
         assert_eq!(s.travel_dir, Direction::Up);
         assert_eq!(s.travelled_dir, Direction::Up);
-        assert_eq!(s.map_size, Size{x:5,y:5});
     }
 
     #[test]
@@ -180,12 +382,11 @@ mod test {
         
         assert_eq!(s.travel_dir, Direction::Up);
         
-        s.set_dir(Direction::Left);
+        s.set_dir(Direction::Left, &board());
 
         assert_eq!(s.segments.len(),1);
         assert_eq!(s.travel_dir, Direction::Left);
         assert_eq!(s.travelled_dir, Direction::Up);
-        assert_eq!(s.map_size, Size{x:5,y:5});
     }
 
     #[test]
@@ -194,50 +395,199 @@ mod test {
         
         assert_eq!(s.travel_dir, Direction::Up);
         
-        s.set_dir(Direction::Down);
+        s.set_dir(Direction::Down, &board());
 
         assert_eq!(s.segments.len(),1);
         assert_eq!(s.travel_dir, Direction::Up);
         assert_eq!(s.travelled_dir, Direction::Up);
-        assert_eq!(s.map_size, Size{x:5,y:5});
     }
 
     #[test]
     fn get_next_head_pos_in_bounds() {
         let mut s = Snake::new([2,2], Size{x:5,y:5});
-        s.set_dir(Direction::Up);
-        assert_eq!(s.get_next_head_pos(),[2,3].into());
-        s.set_dir(Direction::Right);
-        assert_eq!(s.get_next_head_pos(),[3,2].into());
-        s.set_dir(Direction::Left);
-        assert_eq!(s.get_next_head_pos(),[1,2].into());
+        s.set_dir(Direction::Up, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([2,3].into()));
+        s.set_dir(Direction::Right, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([3,2].into()));
+        s.set_dir(Direction::Left, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([1,2].into()));
 
         s.travelled_dir = Direction::Down;
-        s.set_dir(Direction::Down);
-        assert_eq!(s.get_next_head_pos(),[2,1].into());
+        s.set_dir(Direction::Down, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([2,1].into()));
     }
 
     #[test]
     fn get_next_head_pos_out_of_bounds() {
         let mut s = Snake::new([4,4], Size{x:5,y:5});
-        s.set_dir(Direction::Up);
-        assert_eq!(s.get_next_head_pos(),[4,0].into());
-        s.set_dir(Direction::Right);
-        assert_eq!(s.get_next_head_pos(),[0,4].into());
+        s.set_dir(Direction::Up, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([4,0].into()));
+        s.set_dir(Direction::Right, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([0,4].into()));
 
         let mut s = Snake::new([0,0], Size{x:5,y:5});
-        s.set_dir(Direction::Left);
-        assert_eq!(s.get_next_head_pos(),[4,0].into());
+        s.set_dir(Direction::Left, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([4,0].into()));
 
         s.travelled_dir = Direction::Down;
-        s.set_dir(Direction::Down);
-        assert_eq!(s.get_next_head_pos(),[0,4].into());
+        s.set_dir(Direction::Down, &board());
+        assert_eq!(s.get_next_head_pos(&board()),Some([0,4].into()));
     }
 
     #[test]
-    fn head_in_body_detection_false() {
+    fn get_next_head_pos_through_portal() {
+        let topology = board_with_portal([1,2], [3,0]);
+        let s = Snake::new([1,1], Size{x:5,y:5});
+        assert_eq!(s.get_next_head_pos(&topology),Some([3,0].into()));
+
+        // portals work across the edge of the map too
+        let topology = board_with_portal([4,4], [2,0]);
+        let s = Snake::new([2,4], Size{x:5,y:5});
+        assert_eq!(s.get_next_head_pos(&topology),Some([4,4].into()));
+    }
+
+    #[test]
+    fn body_follows_through_portal() {
+        let topology = board_with_portal([1,4], [3,0]);
+        let mut s = Snake::new([1,1], Size{x:5,y:5});
+        s.add_front_segment([1,2]);
+        s.add_front_segment([1,3]);
+        assert_eq!(s.slither(None, &topology),SnakeState::Alive);
+        assert_eq!(s.head_pos(), [3,0].into());
+        assert_eq!(s.slither(None, &topology),SnakeState::Alive);
+        assert_eq!(s.slither(None, &topology),SnakeState::Alive);
+        let seg_vec : Vec<Segment> = vec![[3,2].into(),[3,1].into(),[3,0].into()];
+        assert!(s.iter_segments().eq(seg_vec.iter()));
+        assert!(!s.is_in_snake([1,3]));
+    }
+
+    #[test]
+    fn following_the_tail() {
+        // a 2x2 loop, head at [0,1] about to move onto the tail at [0,0]
+        let loop_snake = || {
+            let body = vec![[0,1].into(),[1,1].into(),[1,0].into(),[0,0].into()];
+            return Snake::from_segments(body, Direction::Down, Size{x:5,y:5});
+        };
+        let mut s = loop_snake();
+        assert_eq!(s.slither(None, &board()),SnakeState::Dead(Reason::Collision));
+
+        let mut s = loop_snake();
+        s.set_collision(CollisionPolicy::Classic);
+        assert_eq!(s.slither(None, &board()),SnakeState::Alive);
+        assert_eq!(s.head_pos(), [0,0].into());
+        // the tail stays put when growing, so it's in the way
+        let mut s = loop_snake();
+        s.set_collision(CollisionPolicy::Classic);
+        assert_eq!(s.slither(Some(FoodGroup::Grow), &board()),SnakeState::Dead(Reason::Collision));
+    }
+
+    #[test]
+    fn occupancy_follows_segments() {
+        let size = Size{x:5,y:5};
+        let in_sync = |s: &Snake| (0..5).all(|x| (0..5).all(|y| {
+            let pos: Position = [x,y].into();
+            s.is_in_snake(pos) == s.iter_segments().any(|seg| seg.pos == pos)
+        }));
+        let body = vec![[2,2].into(),[2,1].into(),[2,0].into(),[1,0].into()];
+        let mut s = Snake::from_segments(body, Direction::Up, size);
+        s.set_collision(CollisionPolicy::Classic);
+        assert!(in_sync(&s));
+        s.slither(None, &board());
+        s.slither(Some(FoodGroup::Grow), &board());
+        assert!(in_sync(&s));
+        s.slither(Some(FoodGroup::Shrink), &board());
+        assert!(in_sync(&s));
+        assert_eq!(s.trim_from([2,3].into()), 2);
+        assert!(in_sync(&s));
+
+        // a ghost crossing itself leaves the cell taken until both are gone
+        let body = vec![[1,1].into(),[1,2].into(),[2,2].into(),[2,1].into(),[1,1].into()];
+        let mut s = Snake::from_segments(body, Direction::Up, size);
+        s.effects_mut().apply(PowerUp::Ghost);
+        assert_eq!(s.slither(None, &board()), SnakeState::Alive);
+        assert!(s.is_in_snake([1,1]));
+        assert!(in_sync(&s));
+    }
+
+    #[test]
+    fn running_into_walls() {
+        let topology = Topology::new(Wrap::Cylinder, Size{x:5,y:5});
         let mut s = Snake::new([4,4], Size{x:5,y:5});
-        assert_eq!(s.next_head_pos_in_body(),false);
+        assert_eq!(s.get_next_head_pos(&topology), None);
+        assert_eq!(s.slither(None, &topology),SnakeState::Dead(Reason::Wall));
+        assert_eq!(s.head_pos(), [4,4].into());
+        // the sides still wrap
+        s.set_dir(Direction::Right, &topology);
+        assert_eq!(s.slither(None, &topology),SnakeState::Alive);
+        assert_eq!(s.head_pos(), [0,4].into());
+    }
+
+    #[test]
+    fn hex_directions() {
+        let mut s = Snake::new([2,2], Size{x:5,y:5});
+        let mut topology = board();
+        topology.set_grid(&crate::game::grid::HEX);
+        s.fit_topology(&topology);
+        // there's no straight up on a hex grid
+        assert_eq!(s.get_dir(), Direction::UpRight);
+        s.set_dir(Direction::Up, &topology);
+        assert_eq!(s.get_next_head_pos(&topology), Some([2,3].into()));
+        s.set_dir(Direction::DownLeft, &topology);
+        assert_eq!(s.get_next_head_pos(&topology), Some([2,3].into()));
+        s.set_dir(Direction::UpLeft, &topology);
+        assert_eq!(s.slither(None, &topology), SnakeState::Alive);
+        assert_eq!(s.head_pos(), [1,3].into());
+        // turning goes round all six, one notch per move
+        s.turn(false, &topology);
+        assert_eq!(s.get_next_head_pos(&topology), Some([0,3].into()));
+        s.turn(true, &topology);
+        s.turn(true, &topology);
+        assert_eq!(s.get_next_head_pos(&topology), Some([1,4].into()));
+    }
+
+    #[test]
+    fn speed_in_fractions_of_a_cell() {
+        let mut s = Snake::new([2,2], Size{x:5,y:5});
+        s.set_speed(SPEED_UNIT+SPEED_UNIT/2);
+        let steps: Vec<usize> = (0..4).map(|_| s.steps_this_tick()).collect();
+        assert_eq!(steps, vec![1,2,1,2]);
+
+        s.set_speed(SPEED_UNIT/4);
+        assert_eq!((0..8).map(|_| s.steps_this_tick()).sum::<usize>(), 2);
+
+        s.set_speed(0);
+        assert_eq!(s.speed(), MIN_SPEED);
+        s.set_speed(100);
+        assert_eq!(s.speed(), MAX_SPEED);
+    }
+
+    #[test]
+    fn parse_collision_policies() {
+        for name in CollisionPolicy::NAMES.iter() {
+            assert_eq!(CollisionPolicy::from_str(name).unwrap().to_string(), *name);
+        }
+        assert!(CollisionPolicy::from_str("bouncy").is_err());
+    }
+
+    #[test]
+    fn collision_across_portal() {
+        let topology = board_with_portal([2,3], [3,0]);
+        let mut s = Snake::new([3,1], Size{x:5,y:5});
+        s.add_front_segment([3,0]);
+        s.add_front_segment([2,0]);
+        s.add_front_segment([2,1]);
+        s.add_front_segment([2,2]);
+        s.travel_dir    = Direction::Up;
+        s.travelled_dir = Direction::Up;
+        // going up lands on [2,3], which sends the head into its own body
+        assert_eq!(s.next_head_pos_in_body(&topology),true);
+        assert_eq!(s.slither(None, &topology),SnakeState::Dead(Reason::Collision));
+    }
+
+    #[test]
+    fn head_in_body_detection_false() {
+        let s = Snake::new([4,4], Size{x:5,y:5});
+        assert_eq!(s.next_head_pos_in_body(&board()),false);
     }
 
     #[test]
@@ -314,9 +664,9 @@ mod test {
         s.add_front_segment([2,2]);
         s.add_front_segment([2,3]);
         s.add_front_segment([3,3]);
-        s.set_dir(Direction::Right);
-        assert_eq!(s.get_next_head_pos(), [4,3].into());
-        assert_eq!(s.next_head_pos_in_body(),true);
+        s.set_dir(Direction::Right, &board());
+        assert_eq!(s.get_next_head_pos(&board()), Some([4,3].into()));
+        assert_eq!(s.next_head_pos_in_body(&board()),true);
     }
 
     #[test]
@@ -328,7 +678,7 @@ mod test {
         s.travel_dir    = Direction::Left;
         s.travelled_dir = Direction::Left;
         
-        assert_eq!(s.slither(None),SnakeState::Alive);
+        assert_eq!(s.slither(None, &board()),SnakeState::Alive);
         let seg_vec = vec![
             [2,3].into(),
             [2,2].into(),
@@ -341,8 +691,8 @@ mod test {
             assert_eq!(s,ref_s);
         });
 
-        s.set_dir(Direction::Up);
-        assert_eq!(s.slither(None),SnakeState::Alive);
+        s.set_dir(Direction::Up, &board());
+        assert_eq!(s.slither(None, &board()),SnakeState::Alive);
         let seg_vec = vec![
             [2,2].into(),
             [2,1].into(),
@@ -360,7 +710,7 @@ mod test {
     fn slither_test_no_food_one_seg() {
         let mut s = Snake::new([3,3], Size{x:5,y:5});
         
-        assert_eq!(s.slither(None),SnakeState::Alive);
+        assert_eq!(s.slither(None, &board()),SnakeState::Alive);
         let seg_vec = vec![
             [3,4].into(),
         ];
@@ -380,7 +730,7 @@ mod test {
         s.travel_dir    = Direction::Left;
         s.travelled_dir = Direction::Left;
         
-        assert_eq!(s.slither(Some(FoodGroup::Grow)),SnakeState::Alive);
+        assert_eq!(s.slither(Some(FoodGroup::Grow), &board()),SnakeState::Alive);
         let seg_vec = vec![
             [3,3].into(),
             [2,3].into(),
@@ -394,8 +744,8 @@ mod test {
             assert_eq!(s,ref_s);
         });
 
-        s.set_dir(Direction::Up);
-        assert_eq!(s.slither(None),SnakeState::Alive);
+        s.set_dir(Direction::Up, &board());
+        assert_eq!(s.slither(None, &board()),SnakeState::Alive);
         let seg_vec = vec![
             [2,3].into(),
             [2,2].into(),
@@ -419,7 +769,35 @@ mod test {
         s.travel_dir    = Direction::Left;
         s.travelled_dir = Direction::Left;
         
-        assert_eq!(s.slither(Some(FoodGroup::Poison)),SnakeState::Dead(Reason::Poison));
+        assert_eq!(s.slither(Some(FoodGroup::Poison), &board()),SnakeState::Dead(Reason::Poison));
+    }
+
+    #[test]
+    fn slither_test_powerup_food() {
+        let mut s = Snake::new([3,3], Size{x:5,y:5});
+        s.add_front_segment([2,3]);
+        s.travel_dir    = Direction::Down;
+        s.travelled_dir = Direction::Down;
+
+        assert_eq!(s.slither(Some(FoodGroup::PowerUp(PowerUp::Magnet)), &board()),SnakeState::Alive);
+        assert_eq!(s.segments.len(),2);
+        assert_eq!(s.head_pos(), [2,2].into());
+        assert!(s.effects().is_active(PowerUp::Magnet));
+    }
+
+    #[test]
+    fn ghost_passes_through_body() {
+        let mut s = Snake::new([4,4], Size{x:5,y:5});
+        s.add_front_segment([4,3]);
+        s.add_front_segment([3,3]);
+        s.add_front_segment([3,4]);
+        s.travel_dir    = Direction::Right;
+        s.travelled_dir = Direction::Right;
+        assert_eq!(s.next_head_pos_in_body(&board()),true);
+
+        s.effects_mut().apply(PowerUp::Ghost);
+        assert_eq!(s.slither(None, &board()),SnakeState::Alive);
+        assert_eq!(s.head_pos(), [4,4].into());
     }
 
     #[test]
@@ -0,0 +1,437 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use super::util::{Direction, Position, Size};
+use super::grid::{self, Grid};
+use super::portal::{self, Portal};
+
+// How the edges of the board join up
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Wrap {
+    // every edge is a wall
+    Rectangle,
+    // leaving one side brings you back on the other, on both axes
+    #[default]
+    Torus,
+    // wraps left and right only; top and bottom are walls
+    Cylinder,
+    // wraps left and right upside down; top and bottom are walls
+    Mobius,
+    // wraps left and right upside down, and top and bottom as normal
+    Klein,
+}
+
+impl Wrap {
+    pub const NAMES: [&'static str; 5] = ["rectangle", "torus", "cylinder", "mobius", "klein"];
+
+    // whether going over the left or right edge turns you upside down
+    pub fn is_twisted(&self) -> bool {
+        return *self == Wrap::Mobius || *self == Wrap::Klein;
+    }
+}
+
+impl fmt::Display for Wrap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Wrap::Rectangle => "rectangle",
+            Wrap::Torus     => "torus",
+            Wrap::Cylinder  => "cylinder",
+            Wrap::Mobius    => "mobius",
+            Wrap::Klein     => "klein",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Wrap {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rectangle" => Ok(Wrap::Rectangle),
+            "torus"     => Ok(Wrap::Torus),
+            "cylinder"  => Ok(Wrap::Cylinder),
+            "mobius"    => Ok(Wrap::Mobius),
+            "klein"     => Ok(Wrap::Klein),
+            _ => Err(format!("unknown topology '{}', expected one of: {}", s, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+// Levels that come with the game, usable by name in place of a file
+pub const LEVELS: [(&str, &str); 2] = [
+    ("circle", include_str!("../../levels/circle.txt")),
+    ("cross",  include_str!("../../levels/cross.txt")),
+];
+
+// The shape of the board: its size, the shape of its cells, which cells can
+// be used, and where you end up when you go over an edge or into a portal.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Topology {
+    wrap: Wrap,
+    grid: &'static dyn Grid,
+    size: Size,
+    // indexed [x][y], false for walls
+    open: Vec<Vec<bool>>,
+    portals: Vec<Portal>,
+}
+
+impl Topology {
+    pub fn new(wrap: Wrap, size: Size) -> Self {
+        Self { wrap, grid: &grid::SQUARE, size, open: vec![vec![true; size.y]; size.x], portals: Vec::new() }
+    }
+
+    // Read a level file. Lines starting with ';' are comments, `wrap <name>`
+    // picks the topology and `grid <name>` the shape of the cells, and the
+    // rest is the board drawn with '.' for open cells and '#' for walls, top
+    // row first. A letter marks one end of a portal, and the same letter
+    // somewhere else marks the other end.
+    pub fn from_level(text: &str) -> Result<Self, String> {
+        let mut wrap = Wrap::default();
+        let mut cells: &'static dyn Grid = &grid::SQUARE;
+        let mut rows: Vec<&str> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("wrap ") {
+                wrap = Wrap::from_str(name.trim())?;
+            } else if let Some(name) = line.strip_prefix("grid ") {
+                cells = grid::from_name(name.trim())?;
+            } else if line.chars().all(|c| c == '.' || c == '#' || c.is_ascii_alphabetic()) {
+                rows.push(line);
+            } else {
+                return Err(format!("line {}: expected a row of '.', '#' and portal letters", index+1));
+            }
+        }
+        let width = rows.first().map(|r| r.len()).ok_or("level has no board")?;
+        if rows.iter().any(|r| r.len() != width) {
+            return Err("level rows are not all the same length".to_string());
+        }
+        let mut topology = Self::new(wrap, [width, rows.len()].into());
+        topology.set_grid(cells);
+        let mut ends: BTreeMap<char, Vec<Position>> = BTreeMap::new();
+        for (from_top, row) in rows.iter().enumerate() {
+            let y = rows.len()-1-from_top;
+            for (x, c) in row.chars().enumerate() {
+                topology.open[x][y] = c != '#';
+                if c.is_ascii_alphabetic() {
+                    ends.entry(c).or_default().push([x,y].into());
+                }
+            }
+        }
+        for (letter, ends) in ends {
+            match ends.as_slice() {
+                [a, b] => topology.add_portal(Portal::new(*a, *b)),
+                _ => return Err(format!("portal '{}' has {} ends, it needs two", letter, ends.len())),
+            }
+        }
+        if topology.cells().is_empty() {
+            return Err("level has nowhere to play".to_string());
+        }
+        return Ok(topology);
+    }
+
+    pub fn wrap(&self) -> Wrap {
+        return self.wrap;
+    }
+
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        self.wrap = wrap;
+    }
+
+    pub fn grid(&self) -> &'static dyn Grid {
+        return self.grid;
+    }
+
+    pub fn set_grid(&mut self, grid: &'static dyn Grid) {
+        self.grid = grid;
+    }
+
+    pub fn size(&self) -> Size {
+        return self.size;
+    }
+
+    // Hex rows lean to the right of the ones below them, so a board joined
+    // upside down would meet rows leaning the other way and the cells
+    // wouldn't line up across the seam.
+    pub fn check(&self) -> Result<(), String> {
+        if self.grid.shape() == grid::Shape::Hex && self.wrap.is_twisted() {
+            return Err(format!("the {} grid can't be joined up as a {}, try torus, cylinder or rectangle", self.grid.name(), self.wrap));
+        }
+        return Ok(());
+    }
+
+    pub fn portals(&self) -> &[Portal] {
+        return &self.portals;
+    }
+
+    pub fn add_portal(&mut self, portal: Portal) {
+        self.portals.push(portal);
+    }
+
+    pub fn is_portal(&self, pos: Position) -> bool {
+        return self.portals.iter().any(|p| p.contains(pos));
+    }
+
+    // follow any portal at `pos` through to its twin
+    pub fn warp(&self, pos: Position) -> Position {
+        return portal::warp(&self.portals, pos);
+    }
+
+    pub fn add_wall(&mut self, pos: Position) {
+        if pos.x < self.size.x && pos.y < self.size.y {
+            self.open[pos.x][pos.y] = false;
+        }
+    }
+
+    // on the board and not a wall
+    pub fn contains(&self, pos: Position) -> bool {
+        return pos.x < self.size.x && pos.y < self.size.y && self.open[pos.x][pos.y];
+    }
+
+    // every open cell
+    pub fn cells(&self) -> Vec<Position> {
+        let mut cells = Vec::new();
+        for x in 0..self.size.x {
+            for y in 0..self.size.y {
+                if self.open[x][y] {
+                    cells.push([x,y].into());
+                }
+            }
+        }
+        return cells;
+    }
+
+    pub fn walls(&self) -> Vec<Position> {
+        let mut walls = Vec::new();
+        for x in 0..self.size.x {
+            for y in 0..self.size.y {
+                if !self.open[x][y] {
+                    walls.push([x,y].into());
+                }
+            }
+        }
+        return walls;
+    }
+
+    // the open cell nearest the middle, for the snake to start on
+    pub fn start(&self) -> Position {
+        let (mid_x, mid_y) = (self.size.x/2, self.size.y/2);
+        let mut cells = self.cells();
+        cells.retain(|pos| !self.is_portal(*pos));
+        let nearest = cells.iter().min_by_key(|p| p.x.abs_diff(mid_x)+p.y.abs_diff(mid_y));
+        return nearest.copied().unwrap_or_else(|| [0,0].into());
+    }
+
+    // One cell on from `pos`, or None if that runs into a wall, off the edge
+    // of the board, or `dir` isn't a way you can go on this grid.
+    pub fn step(&self, pos: Position, dir: Direction) -> Option<Position> {
+        let (dx, dy) = self.grid.offset(dir)?;
+        let (width, height) = (self.size.x as isize, self.size.y as isize);
+        let mut x = pos.x as isize+dx;
+        let mut y = pos.y as isize+dy;
+        // over the left or right edge
+        if x < 0 || x >= width {
+            match self.wrap {
+                Wrap::Rectangle => return None,
+                Wrap::Torus | Wrap::Cylinder => {},
+                Wrap::Mobius | Wrap::Klein => y = height-1-y,
+            }
+            x = x.rem_euclid(width);
+        }
+        // over the top or bottom
+        if y < 0 || y >= height {
+            match self.wrap {
+                Wrap::Torus | Wrap::Klein => y = y.rem_euclid(height),
+                Wrap::Rectangle | Wrap::Cylinder | Wrap::Mobius => return None,
+            }
+        }
+        let next: Position = [x as usize, y as usize].into();
+        if !self.contains(next) {
+            return None;
+        }
+        return Some(next);
+    }
+
+    // Whether going `dir` from `pos` takes you over a twisted edge, after
+    // which anything heading up is heading down and the other way round.
+    pub fn flips(&self, pos: Position, dir: Direction) -> bool {
+        let x = match self.grid.offset(dir) {
+            Some((dx, _)) => pos.x as isize+dx,
+            None          => return false,
+        };
+        return self.wrap.is_twisted() && (x < 0 || x >= self.size.x as isize);
+    }
+
+    // How many steps each cell is from `to`, going round walls and over
+    // edges but not through portals. Indexed [x][y], with None where there's
+    // no way through.
+    pub fn distances(&self, to: Position) -> Vec<Vec<Option<usize>>> {
+        return self.distances_within(to, usize::MAX);
+    }
+
+    // as `distances`, but giving up on anything further than `limit`
+    pub fn distances_within(&self, to: Position, limit: usize) -> Vec<Vec<Option<usize>>> {
+        let mut distances = vec![vec![None; self.size.y]; self.size.x];
+        if !self.contains(to) {
+            return distances;
+        }
+        distances[to.x][to.y] = Some(0);
+        let mut queue = VecDeque::from(vec![(to, 0)]);
+        while let Some((pos, distance)) = queue.pop_front() {
+            if distance >= limit {
+                continue;
+            }
+            // every step can be taken back the other way, so the steps out of
+            // a cell are also the ways into it
+            for dir in self.grid.directions().iter() {
+                if let Some(next) = self.step(pos, *dir) {
+                    if distances[next.x][next.y].is_none() {
+                        distances[next.x][next.y] = Some(distance+1);
+                        queue.push_back((next, distance+1));
+                    }
+                }
+            }
+        }
+        return distances;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SIZE: Size = Size { x: 5, y: 4 };
+
+    #[test]
+    fn parse_wraps() {
+        for name in Wrap::NAMES.iter() {
+            assert_eq!(Wrap::from_str(name).unwrap().to_string(), *name);
+        }
+        assert!(Wrap::from_str("sphere").is_err());
+    }
+
+    #[test]
+    fn edges_of_each_topology() {
+        let right_edge: Position = [4,1].into();
+        let top_edge: Position = [2,3].into();
+        let crossings = |wrap: Wrap| {
+            let t = Topology::new(wrap, SIZE);
+            (t.step(right_edge, Direction::Right), t.step(top_edge, Direction::Up))
+        };
+        assert_eq!(crossings(Wrap::Rectangle), (None, None));
+        assert_eq!(crossings(Wrap::Torus), (Some([0,1].into()), Some([2,0].into())));
+        assert_eq!(crossings(Wrap::Cylinder), (Some([0,1].into()), None));
+        assert_eq!(crossings(Wrap::Mobius), (Some([0,2].into()), None));
+        assert_eq!(crossings(Wrap::Klein), (Some([0,2].into()), Some([2,0].into())));
+        // nothing changes away from the edges
+        let t = Topology::new(Wrap::Rectangle, SIZE);
+        assert_eq!(t.step([2,2].into(), Direction::Down), Some([2,1].into()));
+    }
+
+    #[test]
+    fn every_step_can_be_taken_back() {
+        for wrap in Wrap::NAMES.iter().map(|n| Wrap::from_str(n).unwrap()) {
+            for cells in [&grid::SQUARE as &'static dyn Grid, &grid::HEX].iter() {
+                let mut t = Topology::from_level(".....\n..#..\n.#...\n.....\n").unwrap();
+                t.set_wrap(wrap);
+                t.set_grid(*cells);
+                if t.check().is_err() {
+                    continue;
+                }
+                for pos in t.cells() {
+                    for dir in cells.directions().iter() {
+                        if let Some(next) = t.step(pos, *dir) {
+                            let mut back = dir.opposite();
+                            if t.flips(pos, *dir) {
+                                back = back.flip_vertical();
+                            }
+                            assert_eq!(t.step(next, back), Some(pos), "{} from {:?} on a {} {:?}", dir, pos, wrap, cells);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hex_cant_be_twisted() {
+        let mut t = Topology::new(Wrap::Torus, SIZE);
+        t.set_grid(&grid::HEX);
+        assert!(t.check().is_ok());
+        t.set_wrap(Wrap::Mobius);
+        assert!(t.check().is_err());
+        t.set_wrap(Wrap::Klein);
+        assert!(t.check().is_err());
+        t.set_grid(&grid::SQUARE);
+        assert!(t.check().is_ok());
+    }
+
+    #[test]
+    fn hex_edges() {
+        let mut t = Topology::new(Wrap::Torus, SIZE);
+        t.set_grid(&grid::HEX);
+        assert_eq!(t.step([2,2].into(), Direction::UpLeft), Some([1,3].into()));
+        assert_eq!(t.step([2,2].into(), Direction::DownRight), Some([3,1].into()));
+        assert_eq!(t.step([2,2].into(), Direction::Up), None);
+        // off a corner wraps on both axes at once
+        assert_eq!(t.step([0,3].into(), Direction::UpLeft), Some([4,0].into()));
+        t.set_wrap(Wrap::Cylinder);
+        assert_eq!(t.step([0,3].into(), Direction::UpLeft), None);
+        assert_eq!(t.step([4,1].into(), Direction::DownRight), Some([0,0].into()));
+    }
+
+    #[test]
+    fn load_levels() {
+        let t = Topology::from_level("; a plus\nwrap cylinder\n.#.\n...\n##.\n").unwrap();
+        assert_eq!(t.size(), [3,3].into());
+        assert_eq!(t.wrap(), Wrap::Cylinder);
+        assert_eq!(t.walls(), vec![[0,0].into(), [1,0].into(), [1,2].into()]);
+        assert_eq!(t.start(), [1,1].into());
+        // walls stop the snake, even when wrapping
+        assert_eq!(t.step([1,1].into(), Direction::Up), None);
+        assert_eq!(t.step([0,1].into(), Direction::Left), Some([2,1].into()));
+        assert_eq!(t.step([2,0].into(), Direction::Right), None);
+
+        assert!(Topology::from_level("..\n...\n").is_err());
+        assert!(Topology::from_level("##\n##\n").is_err());
+        assert!(Topology::from_level("wrap torus\n").is_err());
+        assert!(Topology::from_level(".o.\n").is_err());
+        assert!(Topology::from_level("a.a\n..a\n").is_err());
+        assert!(Topology::from_level(".+.\n").is_err());
+        assert!(Topology::from_level("grid triangle\n...\n").is_err());
+        for (name, text) in LEVELS.iter() {
+            assert!(Topology::from_level(text).is_ok(), "{} doesn't load", name);
+        }
+    }
+
+    #[test]
+    fn level_portals() {
+        let t = Topology::from_level("a..\n.b.\nb.a\n").unwrap();
+        assert_eq!(t.portals(), &[Portal::new([0,2],[2,0]), Portal::new([1,1],[0,0])]);
+        assert_eq!(t.warp([2,0].into()), [0,2].into());
+        assert_eq!(t.warp([1,2].into()), [1,2].into());
+        assert!(t.contains([1,1].into()));
+        // the snake doesn't start on a portal
+        assert_ne!(t.start(), [1,1].into());
+    }
+
+    #[test]
+    fn distances_round_walls() {
+        let t = Topology::from_level("wrap rectangle\n...\n##.\n...\n").unwrap();
+        let distances = t.distances([0,2].into());
+        assert_eq!(distances[0][2], Some(0));
+        assert_eq!(distances[2][1], Some(3));
+        // the long way round the wall
+        assert_eq!(distances[0][0], Some(6));
+        assert_eq!(distances[0][1], None);
+        // on a torus it's a step over the edge
+        let t = Topology::new(Wrap::Torus, SIZE);
+        assert_eq!(t.distances([0,0].into())[4][3], Some(2));
+        let near = t.distances_within([0,0].into(), 1);
+        assert_eq!(near[4][0], Some(1));
+        assert_eq!(near[4][3], None);
+    }
+}
@@ -1,3 +1,8 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::powerup::PowerUp;
+
 #[derive(Clone,Copy, Eq, PartialEq,Debug)]
 pub struct Size {
     pub x:usize,
@@ -56,6 +61,9 @@ impl From<Position> for (usize,usize)  {
 #[derive(Clone,Copy, PartialEq, Eq,Debug)]
 pub enum Direction {
     Up,Down,Left,Right,
+    // the snake only goes these ways on the hex grid, though bouncers
+    // cut across square cells with them too
+    UpLeft,UpRight,DownLeft,DownRight,
 }
 
 impl Direction {
@@ -65,8 +73,82 @@ impl Direction {
             Self::Down  => Self::Up,
             Self::Left  => Self::Right,
             Self::Right => Self::Left,
+            Self::UpLeft    => Self::DownRight,
+            Self::UpRight   => Self::DownLeft,
+            Self::DownLeft  => Self::UpRight,
+            Self::DownRight => Self::UpLeft,
         } 
     }
+
+    // the same way, but going left instead of right and right instead of left
+    pub fn flip_horizontal(&self) -> Self {
+        match self {
+            Self::Left      => Self::Right,
+            Self::Right     => Self::Left,
+            Self::UpLeft    => Self::UpRight,
+            Self::UpRight   => Self::UpLeft,
+            Self::DownLeft  => Self::DownRight,
+            Self::DownRight => Self::DownLeft,
+            other => *other,
+        }
+    }
+
+    // the same way, but going down instead of up and up instead of down
+    pub fn flip_vertical(&self) -> Self {
+        match self {
+            Self::Up        => Self::Down,
+            Self::Down      => Self::Up,
+            Self::UpLeft    => Self::DownLeft,
+            Self::DownLeft  => Self::UpLeft,
+            Self::UpRight   => Self::DownRight,
+            Self::DownRight => Self::UpRight,
+            other => *other,
+        }
+    }
+
+    // a diagonal as its up or down part and its left or right part
+    pub fn split(&self) -> Option<(Self, Self)> {
+        match self {
+            Self::UpLeft    => Some((Self::Up, Self::Left)),
+            Self::UpRight   => Some((Self::Up, Self::Right)),
+            Self::DownLeft  => Some((Self::Down, Self::Left)),
+            Self::DownRight => Some((Self::Down, Self::Right)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Up    => "up",
+            Self::Down  => "down",
+            Self::Left  => "left",
+            Self::Right => "right",
+            Self::UpLeft    => "up-left",
+            Self::UpRight   => "up-right",
+            Self::DownLeft  => "down-left",
+            Self::DownRight => "down-right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up"    => Ok(Self::Up),
+            "down"  => Ok(Self::Down),
+            "left"  => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "up-left"    => Ok(Self::UpLeft),
+            "up-right"   => Ok(Self::UpRight),
+            "down-left"  => Ok(Self::DownLeft),
+            "down-right" => Ok(Self::DownRight),
+            _       => Err(format!("unknown direction '{}'", s)),
+        }
+    }
 }
 
 impl std::ops::Add for Position {
@@ -80,17 +162,6 @@ impl std::ops::Add for Position {
 }
 
 
-impl std::ops::Add<Direction> for Position {
-    type Output = Position;
-    fn add(self, rhs: Direction) -> Self::Output {
-        match rhs {
-            Direction::Up    => Position{x:self.x,y:self.y+1},
-            Direction::Down  => Position{x:self.x,y:self.y-1},
-            Direction::Right => Position{x:self.x+1,y:self.y},
-            Direction::Left  => Position{x:self.x-1,y:self.y},
-        }
-    }
-}
 
 
 // Food
@@ -99,6 +170,31 @@ pub enum FoodGroup {
     Grow,
     Shrink,
     Poison,
+    PowerUp(PowerUp),
+}
+
+impl fmt::Display for FoodGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Grow   => "grow",
+            Self::Shrink => "shrink",
+            Self::Poison => "poison",
+            Self::PowerUp(p) => return write!(f, "{}", p),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for FoodGroup {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grow"   => Ok(Self::Grow),
+            "shrink" => Ok(Self::Shrink),
+            "poison" => Ok(Self::Poison),
+            _        => PowerUp::from_str(s).map(Self::PowerUp).map_err(|_| format!("unknown food group '{}'", s)),
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
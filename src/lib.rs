@@ -0,0 +1,6 @@
+#![allow(clippy::needless_return, clippy::bool_assert_comparison)]
+// The game and the terminal UI, as a library the binary and the benchmarks
+// both build on
+pub mod game;
+pub mod ui;
+pub mod config;
@@ -1,20 +1,91 @@
-// use std::process::{exit};
+#![allow(clippy::needless_return, clippy::bool_assert_comparison)]
+use std::process::exit;
 
-use clap::{Arg, App};
-// use tokio::time::{sleep, Duration};
-use std::thread::sleep;
+use clap::{Arg, App, SubCommand};
 use std::time::Duration;
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::panic::{self, AssertUnwindSafe};
+use crossbeam::channel::Receiver;
 
-mod game;
-use game::{GameState,SnakeState};
+use snek::game;
+use game::{GameState,SnakeState,CritterKind,SpawnRules,SpawnStrategy,ScoringRules,CollisionPolicy,SnakeControl,Topology,Wrap,LEVELS,ControlScheme,mode,grid};
 
-mod ui;
-use ui::{UI,UIControl};
+use snek::ui;
+use ui::{UI,UIControl,Theme,RenderMode,Keymap,Action,Key};
+use ui::input::{self, InputSource, Fifo, Socket};
+
+use snek::config;
+use config::Config;
 
 mod util;
 use util::*;
 
+mod shutdown;
+
+mod runtime;
+use runtime::{Runtime, Event};
+
+mod scores;
+use scores::{GameRecord, Stats, Daily};
+
+mod daily;
+use daily::Challenge;
+
+// Everything needed to start a game, from the command line and config file
+struct Settings {
+    // the board's size, shape and edges
+    topology: Topology,
+    mode: String,
+    // whether the mode was asked for, so it wins over a resumed game's own
+    mode_given: bool,
+    theme: Theme,
+    render_mode: RenderMode,
+    controls: ControlScheme,
+    keymap: Keymap,
+    // click on the board to steer, and on menus to pick from them
+    mouse: bool,
+    // more places to take commands from, besides the terminal
+    fifo: Option<PathBuf>,
+    socket: Option<PathBuf>,
+    config_path: Option<PathBuf>,
+    // set up this player's keys instead of playing
+    rebind: Option<usize>,
+    autosave: Option<PathBuf>,
+    resume: Option<PathBuf>,
+    portals: usize,
+    critters: usize,
+    spawn: SpawnRules,
+    target: Option<usize>,
+    scoring: ScoringRules,
+    collision: CollisionPolicy,
+    scores: Option<PathBuf>,
+    // play the daily challenge instead of a game set up by the settings
+    daily: bool,
+    leaderboard_only: bool,
+    // where everyone's daily scores are gathered
+    daily_scores: Option<PathBuf>,
+}
+
+// How a game came to an end
+enum Outcome {
+    Died(String),
+    Won(String),
+    TimeUp(String),
+    Quit,
+    Stopped,
+}
+
 fn main() {
+    if let Err(err) = run() {
+        eprintln!("snek: {}", err);
+        exit(1);
+    }
+}
+
+fn parse_settings() -> Result<Settings, String> {
     // Parse the Args
     let matches = App::new("Snek")
                           .version("1.0")
@@ -25,44 +96,561 @@ fn main() {
                                .value_name("H,W")
                                .help("Sets the game width and height")
                                .takes_value(true))
+                          .arg(Arg::with_name("mode")
+                               .short("m")
+                               .long("mode")
+                               .value_name("MODE")
+                               .help("Sets the game mode: classic, time-attack[:TICKS], survival or zen")
+                               .takes_value(true))
+                          .arg(Arg::with_name("theme")
+                               .short("t")
+                               .long("theme")
+                               .value_name("NAME")
+                               .help("Sets the colour theme")
+                               .possible_values(&Theme::NAMES)
+                               .takes_value(true))
+                          .arg(Arg::with_name("render")
+                               .short("r")
+                               .long("render")
+                               .value_name("MODE")
+                               .help("Sets how the board is drawn")
+                               .possible_values(&RenderMode::NAMES)
+                               .takes_value(true))
+                          .arg(Arg::with_name("controls")
+                               .long("controls")
+                               .value_name("SCHEME")
+                               .help("Steers with arrow keys pointing the way to go (absolute) or left and right turning the snake (relative)")
+                               .possible_values(&ControlScheme::NAMES)
+                               .takes_value(true))
+                          .arg(Arg::with_name("mouse")
+                               .long("mouse")
+                               .help("Steers the snake towards the cell clicked on, in terminals that report the mouse"))
+                          .arg(Arg::with_name("fifo")
+                               .long("fifo")
+                               .value_name("PIPE")
+                               .help("Takes commands (up, down, faster, quit, ...) a line at a time from a named pipe")
+                               .takes_value(true))
+                          .arg(Arg::with_name("socket")
+                               .long("socket")
+                               .value_name("PATH")
+                               .help("Takes commands a line at a time from connections to a Unix socket made at PATH")
+                               .takes_value(true))
+                          .arg(Arg::with_name("config")
+                               .short("c")
+                               .long("config")
+                               .value_name("FILE")
+                               .help("Reads settings from FILE instead of ~/.config/snek/config")
+                               .takes_value(true))
+                          .arg(Arg::with_name("portals")
+                               .short("p")
+                               .long("portals")
+                               .value_name("N")
+                               .help("Places N pairs of portals on the board")
+                               .takes_value(true))
+                          .arg(Arg::with_name("critters")
+                               .long("critters")
+                               .value_name("N")
+                               .help("Puts N critters (mice and hazards) on the board")
+                               .takes_value(true))
+                          .arg(Arg::with_name("spawn")
+                               .long("spawn")
+                               .value_name("STRATEGY")
+                               .help("Where food appears: uniform, away[:N], clustered[:N] or edge")
+                               .takes_value(true))
+                          .arg(Arg::with_name("max-food")
+                               .long("max-food")
+                               .value_name("N")
+                               .help("Keeps at most N foods on the board")
+                               .takes_value(true))
+                          .arg(Arg::with_name("collision")
+                               .long("collision")
+                               .value_name("RULE")
+                               .help("Sets what happens when the snake runs into itself")
+                               .possible_values(&CollisionPolicy::NAMES)
+                               .takes_value(true))
+                          .arg(Arg::with_name("topology")
+                               .long("topology")
+                               .value_name("EDGES")
+                               .help("Sets what happens at the edges of the board")
+                               .possible_values(&Wrap::NAMES)
+                               .takes_value(true))
+                          .arg(Arg::with_name("grid")
+                               .long("grid")
+                               .value_name("CELLS")
+                               .help("Sets the shape of the cells; on a hex grid steer with w e a d z x")
+                               .possible_values(&grid::NAMES)
+                               .takes_value(true))
+                          .arg(Arg::with_name("level")
+                               .long("level")
+                               .value_name("LEVEL")
+                               .help("Plays on a board shaped by a level file, or a built-in level (circle, cross); overrides --dims")
+                               .takes_value(true))
+                          .arg(Arg::with_name("target")
+                               .long("target")
+                               .value_name("LENGTH")
+                               .help("Wins the game once the snake is LENGTH long")
+                               .takes_value(true))
+                          .arg(Arg::with_name("autosave")
+                               .long("autosave")
+                               .value_name("FILE")
+                               .help("Saves the game to FILE if it is quit, killed or crashes")
+                               .takes_value(true))
+                          .arg(Arg::with_name("resume")
+                               .long("resume")
+                               .value_name("FILE")
+                               .help("Carries on a game saved in FILE")
+                               .takes_value(true))
+                          .subcommand(SubCommand::with_name("daily")
+                               .about("Plays today's challenge, the same board for everyone, once a day")
+                               .arg(Arg::with_name("leaderboard")
+                                    .long("leaderboard")
+                                    .help("Shows today's leaderboard without playing")))
+                          .subcommand(SubCommand::with_name("keys")
+                               .about("Sets up the keys, one action at a time, and saves them to the config file")
+                               .arg(Arg::with_name("player")
+                                    .long("player")
+                                    .value_name("N")
+                                    .help("Sets up the keys for player N instead of player 1")
+                                    .takes_value(true)))
                           .get_matches();
 
-    let dim_string = matches.value_of("dims").unwrap_or("10,10");
-    let dims : [usize; 2] = args_to_dims(dim_string).unwrap();
+    let config_path = matches.value_of("config").map(PathBuf::from).or_else(Config::default_path);
+    let config = match &config_path {
+        Some(path) => Config::load(path).map_err(|e| format!("could not read config {}: {}", path.display(), e))?,
+        None       => Config::default(),
+    };
+
+    // command line takes priority over the config file
+    let dim_string = matches.value_of("dims").or_else(|| config.get("dims")).unwrap_or("10,10");
+    let dims = match args_to_dims(dim_string) {
+        Some([w,h]) if w > 0 && h > 0 => [w,h],
+        _ => return Err(format!("invalid dimensions '{}', expected two numbers above zero like 10,10", dim_string)),
+    };
+
+    let mut topology = match matches.value_of("level").or_else(|| config.get("level")) {
+        Some(level) => Topology::from_level(&read_level(level)?).map_err(|e| format!("bad level {}: {}", level, e))?,
+        None        => Topology::new(Wrap::default(), dims.into()),
+    };
+    // a level's own edges can still be overridden
+    if let Some(wrap) = matches.value_of("topology").or_else(|| config.get("topology")) {
+        topology.set_wrap(Wrap::from_str(wrap)?);
+    }
+    if let Some(cells) = matches.value_of("grid").or_else(|| config.get("grid")) {
+        topology.set_grid(grid::from_name(cells)?);
+    }
+    topology.check()?;
+
+    let mode = matches.value_of("mode").or_else(|| config.get("mode")).unwrap_or("classic").to_string();
+    // check the name now, the mode itself is made when the game starts
+    mode::from_name(&mode)?;
+    let mode_given = matches.is_present("mode");
+
+    let theme_name = matches.value_of("theme").or_else(|| config.get("theme")).unwrap_or("default");
+    let theme = Theme::from_str(theme_name)?;
+    let render_name = matches.value_of("render").or_else(|| config.get("render")).unwrap_or("ascii");
+    let render_mode = RenderMode::from_str(render_name)?;
+    if render_mode.by_colour() && !theme.is_coloured() {
+        return Err(format!("the {} theme can't tell items apart in {} mode, try ascii or unicode", theme_name, render_name));
+    }
+    let keymap = Keymap::from_config(&config, 1)?;
+    let rebind = match matches.subcommand_matches("keys") {
+        Some(keys) => {
+            let player = keys.value_of("player").unwrap_or("1");
+            match usize::from_str(player) {
+                Ok(n) if n > 0 => Some(n),
+                _ => return Err(format!("invalid player '{}'", player)),
+            }
+        },
+        None => None,
+    };
+    let controls = ControlScheme::from_str(matches.value_of("controls").or_else(|| config.get("controls")).unwrap_or("absolute"))?;
+    let mouse = matches.is_present("mouse") || match config.get("mouse") {
+        Some("on")        => true,
+        Some("off") | None => false,
+        Some(other)       => return Err(format!("invalid mouse setting '{}', expected on or off", other)),
+    };
+
+    let portal_string = matches.value_of("portals").or_else(|| config.get("portals")).unwrap_or("0");
+    let portals = usize::from_str(portal_string).map_err(|_| format!("invalid portal count '{}'", portal_string))?;
+    let critter_string = matches.value_of("critters").or_else(|| config.get("critters")).unwrap_or("0");
+    let critters = usize::from_str(critter_string).map_err(|_| format!("invalid critter count '{}'", critter_string))?;
+
+    let strategy = SpawnStrategy::from_str(matches.value_of("spawn").or_else(|| config.get("spawn")).unwrap_or("uniform"))?;
+    let max_food = match matches.value_of("max-food").or_else(|| config.get("max-food")) {
+        Some(n) => Some(usize::from_str(n).map_err(|_| format!("invalid food count '{}'", n))?),
+        None    => None,
+    };
+    let target = match matches.value_of("target").or_else(|| config.get("target")) {
+        Some(n) => Some(usize::from_str(n).map_err(|_| format!("invalid target length '{}'", n))?),
+        None    => None,
+    };
+
+    // scoring rules only come from the config file, as `score.<rule> = N`
+    let mut scoring = ScoringRules::default();
+    for key in ScoringRules::KEYS.iter() {
+        if let Some(value) = config.get(&format!("score.{}", key)) {
+            scoring.set(key, value)?;
+        }
+    }
+
+    let collision_name = matches.value_of("collision").or_else(|| config.get("collision")).unwrap_or("strict");
+    let collision = CollisionPolicy::from_str(collision_name)?;
+
+    let scores_path = config.get("scores").map(PathBuf::from).or_else(scores::default_path);
+    let daily = matches.subcommand_matches("daily");
+    if daily.is_some() && matches.is_present("resume") {
+        return Err("the daily challenge can't be resumed".to_string());
+    }
+
+    return Ok(Settings {
+        topology,
+        mode,
+        mode_given,
+        theme,
+        render_mode,
+        controls,
+        keymap,
+        mouse,
+        fifo: matches.value_of("fifo").or_else(|| config.get("fifo")).map(PathBuf::from),
+        socket: matches.value_of("socket").or_else(|| config.get("socket")).map(PathBuf::from),
+        config_path,
+        rebind,
+        autosave: matches.value_of("autosave").or_else(|| config.get("autosave")).map(PathBuf::from),
+        resume: matches.value_of("resume").map(PathBuf::from),
+        portals,
+        critters,
+        spawn: SpawnRules { strategy, max_food },
+        target,
+        scoring,
+        collision,
+        scores: scores_path.clone(),
+        daily: daily.is_some(),
+        leaderboard_only: daily.is_some_and(|d| d.is_present("leaderboard")),
+        daily_scores: config.get("daily-scores").map(PathBuf::from).or(scores_path),
+    });
+}
+
+// the text of a built-in level, or of a level file
+fn read_level(level: &str) -> Result<String, String> {
+    if let Some((_, text)) = LEVELS.iter().find(|(name, _)| *name == level) {
+        return Ok(text.to_string());
+    }
+    return std::fs::read_to_string(level).map_err(|e| format!("could not read level {}: {}", level, e));
+}
+
+fn save_game(gs: &GameState, path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("could not save to {}: {}", path.display(), e))?;
+    return gs.save(file).map_err(|e| format!("could not save to {}: {}", path.display(), e));
+}
+
+fn load_scores(path: &Path) -> Result<Vec<GameRecord>, String> {
+    return scores::load(path).map_err(|e| format!("could not read scores from {}: {}", path.display(), e));
+}
+
+fn append_score(path: &Path, game: &GameRecord) -> Result<(), String> {
+    return scores::append(path, game).map_err(|e| format!("could not record score in {}: {}", path.display(), e));
+}
+
+fn print_leaderboard(settings: &Settings, challenge: &Challenge) -> Result<(), String> {
+    let records = match &settings.daily_scores {
+        Some(path) => load_scores(path)?,
+        None       => Vec::new(),
+    };
+    println!("Leaderboard for {} ({})", challenge.date, challenge.mode);
+    for (place, game) in daily::leaderboard(&records, challenge.date).iter().enumerate() {
+        let player = game.daily.as_ref().map(|d| d.player.as_str()).unwrap_or("");
+        println!("{:>3}. {:<16} {}", place+1, player, game.score);
+    }
+    return Ok(());
+}
 
-    // Start the Game
-    let out: Option<String>;
-    // Use additional scope here to ensure UI element is dropped before we print
-    // the final score. This stops the terminal from being mucked up.
+// Set up a fresh game, either from the settings or from the daily challenge
+fn new_game(settings: &Settings, challenge: Option<&Challenge>) -> Result<GameState, String> {
+    let mut gs = match challenge {
+        Some(challenge) => {
+            let mut gs = GameState::new(challenge.dims,[0,0]);
+            // seed before anything random happens, so the whole board matches
+            gs.set_seed(challenge.seed);
+            gs.add_random_portals(challenge.portals);
+            for kind in CritterKind::ALL.iter().cycle().take(challenge.critters) {
+                gs.spawn_critter(*kind);
+            }
+            gs.set_spawn_rules(challenge.spawn);
+            gs.set_mode(mode::from_name(challenge.mode)?);
+            gs
+        },
+        None => {
+            let mut gs = GameState::new(settings.topology.size(),[0,0]);
+            gs.set_topology(settings.topology.clone());
+            gs.add_random_portals(settings.portals);
+            for kind in CritterKind::ALL.iter().cycle().take(settings.critters) {
+                gs.spawn_critter(*kind);
+            }
+            gs.set_spawn_rules(settings.spawn);
+            gs.set_target_length(settings.target);
+            gs.set_scoring_rules(settings.scoring);
+            gs.set_collision_policy(settings.collision);
+            gs.set_mode(mode::from_name(&settings.mode)?);
+            gs
+        },
+    };
+    gs.gen_food();
+    return Ok(gs);
+}
+
+// Ask for a key for each action in turn, then write them to the config file
+fn rebind_keys(settings: &Settings, player: usize) -> Result<(), String> {
+    let path = settings.config_path.as_ref().ok_or("nowhere to save the keys, as there's no config file")?;
+    let mut config = Config::load(path).map_err(|e| format!("could not read config {}: {}", path.display(), e))?;
+    let mut keymap = Keymap::from_config(&config, player)?;
+    let mut changed = Vec::new();
     {
-        let mut gs = GameState::new(dims,[0,0]);
-        gs.gen_food();
-        let mut ui = UI::new().unwrap();
+        let mut ui: UI = UI::new(settings.theme.clone(), settings.render_mode, settings.mouse).map_err(|e| format!("terminal error: {}", e))?;
         ui.clear();
-        loop {
-            let control = ui.get_control();
-            if control==UIControl::ExitProgram {
-                ui.clear();
-                out = None;
-                break;
+        let mut runtime = Runtime::new(input::start(vec![<UI>::terminal_input()]));
+        for action in Action::ALL.iter() {
+            let current: Vec<String> = keymap.keys(*action).iter().map(Key::to_string).collect();
+            let mut prompt = format!("Player {}: press a key for {}...", player, action);
+            loop {
+                ui.menu(vec![prompt.clone(), format!("now: {}", current.join(" "))], vec![
+                    (Key::Char(' '), "[space] keep it".to_string()),
+                    (Key::Esc, "[esc] give up".to_string()),
+                ]);
+                let key = loop {
+                    match runtime.next() {
+                        Event::Input(input) => if let Some(key) = ui.menu_key(input) {
+                            break key;
+                        },
+                        Event::Stop => break Key::Esc,
+                        Event::Tick | Event::Frame => {},
+                    }
+                };
+                match key {
+                    Key::Char(' ') => break,
+                    Key::Esc | Key::Ctrl('c') => {
+                        ui.clear();
+                        println!("Keys left as they were");
+                        return Ok(());
+                    },
+                    // it couldn't be read back from the config, so ask again
+                    key if !key.can_save() => {
+                        prompt = format!("Player {}: that key can't be saved, press another for {}...", player, action);
+                    },
+                    key => {
+                        keymap.bind(*action, &[key]);
+                        changed.push((*action, key));
+                        break;
+                    },
+                }
             }
-            // update the game state base on user input
-            // render out the game for the ui
-            let (ss,rd) = gs.update_and_render(control.get_snake_control(), true);
-            if let SnakeState::Dead(reason) = ss {
-                ui.clear();
-                out = Some(format!("You died by {:?}, score: {}",reason, rd.score));
-                break;
-            }
-            // update ui
-            ui.render(rd);
-            // wait a while
-            sleep(Duration::from_millis(500));
+        }
+        ui.clear();
+    }
+    let prefix = Keymap::config_prefix(player);
+    for (action, key) in changed.iter() {
+        config.set(&format!("{}key.{}", prefix, action), &key.to_string());
+    }
+    config.save(path).map_err(|e| format!("could not save config {}: {}", path.display(), e))?;
+    println!("Keys saved to {}", path.display());
+    return Ok(());
+}
+
+fn run() -> Result<(), String> {
+    let settings = parse_settings()?;
+    if let Some(player) = settings.rebind {
+        return rebind_keys(&settings, player);
+    }
+
+    let challenge = if settings.daily { Some(Challenge::for_date(daily::today())) } else { None };
+    if let Some(challenge) = &challenge {
+        let played = match &settings.scores {
+            Some(path) => daily::has_played(&load_scores(path)?, challenge.date, &daily::player()),
+            None       => false,
         };
+        if played || settings.leaderboard_only {
+            if played {
+                println!("You've already had a go at the challenge for {}", challenge.date);
+            }
+            return print_leaderboard(&settings, challenge);
+        }
+    }
+
+    let mut gs = match &settings.resume {
+        Some(path) => {
+            let file = File::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+            let mut gs = GameState::load(BufReader::new(file)).map_err(|e| format!("could not resume {}: {}", path.display(), e))?;
+            gs.set_spawn_rules(settings.spawn);
+            gs.set_target_length(settings.target);
+            gs.set_scoring_rules(settings.scoring);
+            gs.set_collision_policy(settings.collision);
+            if settings.mode_given {
+                gs.set_mode(mode::from_name(&settings.mode)?);
+            }
+            gs
+        },
+        None => new_game(&settings, challenge.as_ref())?,
+    };
+
+    // open these before taking over the terminal, so any problem is seen
+    let mut sources: Vec<Box<dyn InputSource>> = Vec::new();
+    if let Some(path) = &settings.fifo {
+        sources.push(Box::new(Fifo::open(path).map_err(|e| format!("could not read from {}: {}", path.display(), e))?));
     }
+    if let Some(path) = &settings.socket {
+        sources.push(Box::new(Socket::bind(path).map_err(|e| format!("could not listen on {}: {}", path.display(), e))?));
+    }
+
+    shutdown::install_panic_hook();
+    let stop = shutdown::register_signals().map_err(|e| format!("could not handle signals: {}", e))?;
 
-    if let Some(line) = out {
-        println!("{}",line);
+    // The UI lives inside `play`, so by the time we get here it has been
+    // dropped and the terminal restored, even if the game panicked.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        play(&mut gs, &settings, sources, stop)
+    }));
+    // anything that went wrong off the main thread, now it can be read
+    for message in shutdown::take_background_panics() {
+        eprintln!("snek: {}", message);
     }
 
-}
\ No newline at end of file
+    // finished games go into the scores file
+    let record = |gs: &GameState, won: bool| -> Result<(), String> {
+        let points = gs.score_breakdown();
+        println!("{} - food: {}, combo: {}, early: {}, length: {}, survival: {}, penalties: -{}",
+                 gs.mode_name(), points.food, points.combo, points.early, points.length, points.survival, points.penalties);
+        let game = GameRecord {
+            score: gs.score(),
+            length: gs.snake_length(),
+            won,
+            perfect: won && gs.is_perfect(),
+            daily: challenge.as_ref().map(|c| Daily { date: c.date.to_string(), player: daily::player() }),
+        };
+        if let Some(path) = &settings.scores {
+            append_score(path, &game)?;
+            let stats = Stats::from_records(&load_scores(path)?);
+            println!("games: {}, wins: {}, perfect: {}, best: {}", stats.games, stats.wins, stats.perfect, stats.best);
+        }
+        if let Some(challenge) = &challenge {
+            if let Some(path) = &settings.daily_scores {
+                if settings.scores.as_ref() != Some(path) {
+                    append_score(path, &game)?;
+                }
+            }
+            print_leaderboard(&settings, challenge)?;
+        }
+        return Ok(());
+    };
+
+    // A game cut short is saved to carry on later, but a daily challenge
+    // gets one go, so it's recorded as it stands however it ended
+    let cut_short = |gs: &GameState| -> Result<(), String> {
+        if challenge.is_some() {
+            return record(gs, false);
+        }
+        if let Some(path) = &settings.autosave {
+            save_game(gs, path)?;
+            eprintln!("snek: game saved to {}", path.display());
+        }
+        return Ok(());
+    };
+
+    match result {
+        Ok(Ok(Outcome::Died(line))) => {
+            println!("{}",line);
+            record(&gs, false)?;
+        },
+        Ok(Ok(Outcome::Won(line)))  => {
+            println!("{}",line);
+            record(&gs, true)?;
+        },
+        Ok(Ok(Outcome::TimeUp(line))) => {
+            println!("{}",line);
+            record(&gs, false)?;
+        },
+        Ok(Ok(Outcome::Quit))       => cut_short(&gs)?,
+        Ok(Ok(Outcome::Stopped))    => {
+            cut_short(&gs)?;
+            eprintln!("snek: stopped by signal");
+        },
+        Ok(Err(err)) => {
+            cut_short(&gs)?;
+            return Err(format!("terminal error: {}", err));
+        },
+        Err(_) => {
+            cut_short(&gs)?;
+            let message = shutdown::take_panic_message().unwrap_or_default();
+            return Err(format!("crashed: {}", message));
+        },
+    }
+    return Ok(());
+}
+
+// how long a tick of the game lasts, and how often the board is redrawn
+// between them
+const TICK: Duration = Duration::from_millis(500);
+const FRAME: Duration = Duration::from_millis(1000/30);
+
+fn play(gs: &mut GameState, settings: &Settings, mut sources: Vec<Box<dyn InputSource>>, stop: Receiver<()>) -> Result<Outcome, io::Error> {
+    let mut ui: UI = UI::new(settings.theme.clone(), settings.render_mode, settings.mouse)?;
+    ui.set_keymap(settings.keymap.clone());
+    ui.clear();
+    sources.insert(0, <UI>::terminal_input());
+    let mut runtime = Runtime::new(input::start(sources));
+    runtime.set_ticks(TICK);
+    runtime.set_frames(FRAME);
+    runtime.set_stop(stop);
+    loop {
+        match runtime.next() {
+            Event::Stop => {
+                ui.clear();
+                return Ok(Outcome::Stopped);
+            },
+            Event::Input(input) => {
+                let control = ui.control(input);
+                if control==UIControl::ExitProgram {
+                    ui.clear();
+                    return Ok(Outcome::Quit);
+                }
+                if control==UIControl::Resize {
+                    ui.clear();
+                    runtime.redraw();
+                }
+                let control = settings.controls.apply(control.get_snake_control());
+                if control != SnakeControl::None {
+                    gs.steer(control);
+                    // show the snake's new heading without waiting for the
+                    // next frame
+                    runtime.redraw();
+                }
+            },
+            Event::Frame => ui.render(gs.get_render_changes()),
+            Event::Tick => {
+                let (ss,rd) = gs.update_and_render(SnakeControl::None, true);
+                if let SnakeState::Dead(reason) = ss {
+                    ui.clear();
+                    return Ok(Outcome::Died(format!("You died by {:?}, score: {}",reason, rd.score)));
+                }
+                if ss == SnakeState::TimeUp {
+                    ui.clear();
+                    return Ok(Outcome::TimeUp(format!("Time's up, score: {}", rd.score)));
+                }
+                if ss == SnakeState::Won {
+                    let perfect = gs.is_perfect();
+                    ui.victory(rd.score, perfect);
+                    // until a key is pressed or the prompt clicked
+                    loop {
+                        match runtime.next() {
+                            Event::Input(input) if ui.menu_key(input).is_some() => break,
+                            Event::Stop => break,
+                            _ => {},
+                        }
+                    }
+                    ui.clear();
+                    let line = if perfect { "You won a perfect game" } else { "You won" };
+                    return Ok(Outcome::Won(format!("{}, score: {}", line, rd.score)));
+                }
+                ui.render(rd);
+            },
+        }
+    }
+}
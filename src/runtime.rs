@@ -0,0 +1,195 @@
+use std::time::{Duration, Instant};
+use crossbeam::channel::{after, never, select, Receiver};
+
+use crate::ui::Input;
+use crate::util::reschedule;
+
+// how much queued input is dealt with before an overdue timer gets its turn,
+// so a source sending without pause can't hold up the game
+const INPUT_BURST: usize = 8;
+
+// Something the game loop has to deal with
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    // a key, click, resize or command from any of the input sources
+    Input(Input),
+    // time for the game to move on a tick
+    Tick,
+    // time to redraw the board
+    Frame,
+    // asked to stop, by a signal or by every input source going away
+    Stop,
+}
+
+// One loop waiting on every source of events at once: input, the tick and
+// frame timers, and signals. Nothing is polled, so the loop sleeps until
+// there's something to do and reacts to it straight away.
+pub struct Runtime {
+    inputs: Receiver<Input>,
+    stop: Receiver<()>,
+    // when the next tick and frame are due, and how far apart they come
+    tick: Option<(Instant, Duration)>,
+    frame: Option<(Instant, Duration)>,
+    // input dealt with since a timer last went off
+    burst: usize,
+}
+
+impl Runtime {
+    pub fn new(inputs: Receiver<Input>) -> Self {
+        Self { inputs, stop: never(), tick: None, frame: None, burst: 0 }
+    }
+
+    pub fn set_ticks(&mut self, period: Duration) {
+        self.tick = Some((Instant::now()+period, period));
+    }
+
+    pub fn set_frames(&mut self, period: Duration) {
+        self.frame = Some((Instant::now(), period));
+    }
+
+    pub fn set_stop(&mut self, stop: Receiver<()>) {
+        self.stop = stop;
+    }
+
+    // draw the next frame as soon as possible, rather than when it's due
+    pub fn redraw(&mut self) {
+        if let Some((due, _)) = &mut self.frame {
+            *due = Instant::now();
+        }
+    }
+
+    // Wait for the next event. A stop comes before anything else, and input
+    // before the timers, so the snake turns before it moves even when the
+    // loop is running behind, up to a burst of input at a time.
+    pub fn next(&mut self) -> Event {
+        if self.stop.try_recv().is_ok() {
+            return Event::Stop;
+        }
+        // ticks win over frames due at the same moment, as they draw too
+        let timer = match (self.tick, self.frame) {
+            (Some((tick, _)), Some((frame, _))) if frame < tick => Some((frame, Event::Frame)),
+            (Some((tick, _)), _) => Some((tick, Event::Tick)),
+            (None, Some((frame, _))) => Some((frame, Event::Frame)),
+            (None, None) => None,
+        };
+        let overdue = timer.filter(|(due, _)| *due <= Instant::now());
+        if self.burst < INPUT_BURST || overdue.is_none() {
+            if let Ok(input) = self.inputs.try_recv() {
+                self.burst += 1;
+                return Event::Input(input);
+            }
+        }
+        if let Some((_, event)) = overdue {
+            return self.fire(event);
+        }
+        let alarm = match timer {
+            Some((due, _)) => after(due.saturating_duration_since(Instant::now())),
+            None           => never(),
+        };
+        select! {
+            recv(self.stop) -> _ => Event::Stop,
+            recv(self.inputs) -> input => match input {
+                Ok(input) => {
+                    self.burst += 1;
+                    Event::Input(input)
+                },
+                Err(_)    => Event::Stop,
+            },
+            recv(alarm) -> _ => match timer {
+                Some((_, event)) => self.fire(event),
+                None             => Event::Stop,
+            },
+        }
+    }
+
+    // Set the timers going again after one of them has gone off
+    fn fire(&mut self, event: Event) -> Event {
+        let now = Instant::now();
+        self.burst = 0;
+        // Each timer keeps to its schedule however long dealing with the last
+        // event took, but after falling well behind (say the game was
+        // suspended) starts afresh rather than rushing to catch up. A tick
+        // draws the board, so puts the frame off.
+        if event == Event::Tick {
+            if let Some((due, period)) = &mut self.tick {
+                *due = reschedule(*due, *period, now);
+            }
+        }
+        if let Some((due, period)) = &mut self.frame {
+            *due = if event == Event::Frame { reschedule(*due, *period, now) } else { now+*period };
+        }
+        return event;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crossbeam::channel::{bounded, unbounded};
+    use crate::ui::Key;
+
+    #[test]
+    fn input_before_timers() {
+        let (sender, receiver) = bounded::<Input>(1);
+        let mut runtime = Runtime::new(receiver);
+        runtime.set_ticks(Duration::from_millis(20));
+        runtime.set_frames(Duration::from_millis(5));
+        // a frame is due straight away
+        assert_eq!(runtime.next(), Event::Frame);
+        sender.send(Input::Key(Key::Up)).unwrap();
+        assert_eq!(runtime.next(), Event::Input(Input::Key(Key::Up)));
+
+        // a few frames go by for every tick
+        let start = Instant::now();
+        let mut frames = 0;
+        while runtime.next() == Event::Frame {
+            frames += 1;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(15));
+        assert!(frames >= 2, "only {} frames", frames);
+    }
+
+    #[test]
+    fn ticks_through_a_flood_of_input() {
+        let (sender, receiver) = unbounded::<Input>();
+        let mut runtime = Runtime::new(receiver);
+        runtime.set_ticks(Duration::from_millis(10));
+        // a client sending far more than can be dealt with in a tick
+        for _ in 0..10000 {
+            sender.send(Input::Key(Key::Up)).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+        let mut inputs = 0;
+        while runtime.next() != Event::Tick {
+            inputs += 1;
+        }
+        assert!(inputs <= INPUT_BURST, "{} inputs before the tick", inputs);
+    }
+
+    #[test]
+    fn stopping() {
+        let (sender, receiver) = bounded::<Input>(1);
+        let (stop_sender, stop) = unbounded();
+        let mut runtime = Runtime::new(receiver);
+        runtime.set_stop(stop);
+        sender.send(Input::Resize(80, 24)).unwrap();
+        stop_sender.send(()).unwrap();
+        assert_eq!(runtime.next(), Event::Stop);
+        assert_eq!(runtime.next(), Event::Input(Input::Resize(80, 24)));
+        // with no input left to come, there's nothing more to wait for
+        drop(sender);
+        assert_eq!(runtime.next(), Event::Stop);
+    }
+
+    #[test]
+    fn redraw_brings_the_frame_forward() {
+        let (_sender, receiver) = bounded::<Input>(1);
+        let mut runtime = Runtime::new(receiver);
+        runtime.set_frames(Duration::from_secs(60));
+        assert_eq!(runtime.next(), Event::Frame);
+        runtime.redraw();
+        let start = Instant::now();
+        assert_eq!(runtime.next(), Event::Frame);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// One finished game, stored as a line of the scores file:
+//
+//   <score> <length> died|won|perfect [daily <date> <player>]
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameRecord {
+    pub score: usize,
+    pub length: usize,
+    pub won: bool,
+    // won by filling the board without ever shrinking
+    pub perfect: bool,
+    // set for a go at the daily challenge
+    pub daily: Option<Daily>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Daily {
+    pub date: String,
+    pub player: String,
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = if self.perfect {
+            "perfect"
+        } else if self.won {
+            "won"
+        } else {
+            "died"
+        };
+        write!(f, "{} {} {}", self.score, self.length, result)?;
+        if let Some(daily) = &self.daily {
+            write!(f, " daily {} {}", daily.date, daily.player)?;
+        }
+        return Ok(());
+    }
+}
+
+impl GameRecord {
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let score = usize::from_str(fields.next()?).ok()?;
+        let length = usize::from_str(fields.next()?).ok()?;
+        let (won, perfect) = match fields.next()? {
+            "died"    => (false, false),
+            "won"     => (true, false),
+            "perfect" => (true, true),
+            _ => return None,
+        };
+        let daily = match fields.next() {
+            Some("daily") => Some(Daily { date: fields.next()?.to_string(), player: fields.next()?.to_string() }),
+            Some(_) => return None,
+            None    => None,
+        };
+        return Some(Self { score, length, won, perfect, daily });
+    }
+}
+
+// Totals over every game in the scores file
+#[derive(Default, PartialEq, Debug)]
+pub struct Stats {
+    pub games: usize,
+    pub wins: usize,
+    pub perfect: usize,
+    pub best: usize,
+}
+
+impl Stats {
+    pub fn from_records(records: &[GameRecord]) -> Self {
+        return Self {
+            games: records.len(),
+            wins: records.iter().filter(|r| r.won).count(),
+            perfect: records.iter().filter(|r| r.perfect).count(),
+            best: records.iter().map(|r| r.score).max().unwrap_or(0),
+        };
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("XDG_DATA_HOME") {
+        return Some(PathBuf::from(dir).join("snek").join("scores"));
+    }
+    if let Ok(home) = std::env::var("HOME") {
+        return Some(PathBuf::from(home).join(".local").join("share").join("snek").join("scores"));
+    }
+    return None;
+}
+
+// a missing file means no games played yet; lines that can't be read are skipped
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<GameRecord>, io::Error> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text.lines().filter_map(GameRecord::from_line).collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn append<P: AsRef<Path>>(path: P, record: &GameRecord) -> Result<(), io::Error> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    return writeln!(file, "{}", record);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn records_round_trip() {
+        let records = [
+            GameRecord { score: 4, length: 3, won: false, perfect: false, daily: None },
+            GameRecord { score: 30, length: 25, won: true, perfect: true, daily: None },
+            GameRecord { score: 12, length: 10, won: true, perfect: false,
+                         daily: Some(Daily { date: "2026-10-19".to_string(), player: "ann".to_string() }) },
+        ];
+        for record in records.iter() {
+            assert_eq!(GameRecord::from_line(&record.to_string()), Some(record.clone()));
+        }
+        assert_eq!(GameRecord::from_line("7 2 won daily 2026-10-19"), None);
+        assert_eq!(GameRecord::from_line("7 2 drew"), None);
+        assert_eq!(GameRecord::from_line("seven"), None);
+
+        let stats = Stats::from_records(&records);
+        assert_eq!(stats, Stats { games: 3, wins: 2, perfect: 1, best: 30 });
+    }
+
+    #[test]
+    fn append_and_load() {
+        let path = std::env::temp_dir().join(format!("snek-scores-test-{}", std::process::id())).join("scores");
+        let record = GameRecord { score: 9, length: 4, won: true, perfect: false, daily: None };
+        append(&path, &record).unwrap();
+        append(&path, &record).unwrap();
+        assert_eq!(load(&path).unwrap(), vec![record.clone(), record]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(load(&path).unwrap(), Vec::new());
+    }
+}
@@ -0,0 +1,102 @@
+use std::any::Any;
+use std::io;
+use std::panic::{self, PanicHookInfo};
+use std::sync::Mutex;
+use std::thread;
+use crossbeam::channel::{unbounded, Receiver};
+
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+// The message from the last panic on the main thread, held until the
+// terminal has been restored and it can be printed readably.
+static PANIC_MESSAGE: Mutex<Option<String>> = Mutex::new(None);
+
+// Panics on other threads, like the ones reading input, don't stop the game
+// but are held to be printed once it's over.
+static BACKGROUND_PANICS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn payload_text(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        return s;
+    }
+    if let Some(s) = payload.downcast_ref::<String>() {
+        return s.as_str();
+    }
+    return "unknown error";
+}
+
+fn format_panic(info: &PanicHookInfo) -> String {
+    let text = payload_text(info.payload());
+    match info.location() {
+        Some(loc) => format!("{} ({}:{})", text, loc.file(), loc.line()),
+        None      => text.to_string(),
+    }
+}
+
+// Replace the default panic hook, which would print into a terminal still in
+// raw mode. The message is stored instead, and picked up with
+// `take_panic_message` or `take_background_panics` once the UI has been
+// dropped.
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let text = format_panic(info);
+        match thread::current().name() {
+            Some("main") => if let Ok(mut message) = PANIC_MESSAGE.lock() {
+                *message = Some(text);
+            },
+            name => if let Ok(mut panics) = BACKGROUND_PANICS.lock() {
+                panics.push(format!("{} thread: {}", name.unwrap_or("background"), text));
+            },
+        }
+    }));
+}
+
+pub fn take_panic_message() -> Option<String> {
+    return PANIC_MESSAGE.lock().ok().and_then(|mut message| message.take());
+}
+
+pub fn take_background_panics() -> Vec<String> {
+    return BACKGROUND_PANICS.lock().map(|mut panics| panics.drain(..).collect()).unwrap_or_default();
+}
+
+// Returns a channel which gets a message each time the program is asked to
+// stop by SIGTERM, SIGHUP or SIGINT, so the main loop can shut down cleanly.
+pub fn register_signals() -> Result<Receiver<()>, io::Error> {
+    let mut signals = Signals::new([SIGTERM, SIGHUP, SIGINT])?;
+    let (sender, receiver) = unbounded();
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            if sender.send(()).is_err() {
+                return;
+            }
+        }
+    });
+    return Ok(receiver);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn panic_payload_text() {
+        let s: Box<dyn Any + Send> = Box::new("static message");
+        assert_eq!(payload_text(s.as_ref()), "static message");
+        let s: Box<dyn Any + Send> = Box::new(String::from("owned message"));
+        assert_eq!(payload_text(s.as_ref()), "owned message");
+        let s: Box<dyn Any + Send> = Box::new(5);
+        assert_eq!(payload_text(s.as_ref()), "unknown error");
+    }
+
+    #[test]
+    fn background_panics_are_kept() {
+        install_panic_hook();
+        let reader = thread::Builder::new().name("reader".to_string()).spawn(|| panic!("lost the pipe")).unwrap();
+        assert!(reader.join().is_err());
+        // put the default hook back for the other tests
+        let _ = panic::take_hook();
+        let panics = take_background_panics();
+        assert!(panics.iter().any(|p| p.starts_with("reader thread: lost the pipe")), "{:?}", panics);
+        assert!(take_background_panics().is_empty());
+    }
+}
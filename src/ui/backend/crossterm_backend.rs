@@ -0,0 +1,122 @@
+use std::io;
+use crossbeam::channel::{Sender, TrySendError};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::{execute, terminal};
+use tui::backend::{Backend, CrosstermBackend};
+use tui::buffer::Cell;
+use tui::layout::Rect;
+
+use super::{Input, Key, TerminalBackend};
+
+impl From<KeyEvent> for Key {
+    fn from(key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Up    => Key::Up,
+            KeyCode::Down  => Key::Down,
+            KeyCode::Left  => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Esc   => Key::Esc,
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+            KeyCode::Char(c) => Key::Char(c),
+            _                => Key::Other,
+        }
+    }
+}
+
+fn to_input(evt: Event) -> Option<Input> {
+    match evt {
+        Event::Key(key)     => Some(Input::Key(key.into())),
+        Event::Resize(w, h) => Some(Input::Resize(w, h)),
+        Event::Mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column, row, .. }) => Some(Input::Click(column, row)),
+        Event::Mouse(_)     => None,
+    }
+}
+
+// crossterm needs raw mode and mouse capture turning off by hand, so the
+// backend is wrapped to do that when it is dropped.
+pub struct CrosstermTerminal {
+    backend: CrosstermBackend<io::Stdout>,
+    mouse: bool,
+}
+
+impl Drop for CrosstermTerminal {
+    fn drop(&mut self) {
+        if self.mouse {
+            let _ = execute!(io::stdout(), DisableMouseCapture);
+        }
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Backend for CrosstermTerminal {
+    fn draw<'a, I>(&mut self, content: I) -> Result<(), io::Error>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)> {
+        self.backend.draw(content)
+    }
+    fn hide_cursor(&mut self) -> Result<(), io::Error> {
+        self.backend.hide_cursor()
+    }
+    fn show_cursor(&mut self) -> Result<(), io::Error> {
+        self.backend.show_cursor()
+    }
+    fn get_cursor(&mut self) -> Result<(u16, u16), io::Error> {
+        self.backend.get_cursor()
+    }
+    fn set_cursor(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
+        self.backend.set_cursor(x, y)
+    }
+    fn clear(&mut self) -> Result<(), io::Error> {
+        self.backend.clear()
+    }
+    fn size(&self) -> Result<Rect, io::Error> {
+        self.backend.size()
+    }
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.backend.flush()
+    }
+}
+
+impl TerminalBackend for CrosstermTerminal {
+    fn open(mouse: bool) -> Result<Self, io::Error> {
+        terminal::enable_raw_mode().map_err(io::Error::other)?;
+        if mouse {
+            execute!(io::stdout(), EnableMouseCapture).map_err(io::Error::other)?;
+        }
+        return Ok(Self { backend: CrosstermBackend::new(io::stdout()), mouse });
+    }
+
+    fn read_input(sender: Sender<Input>) {
+        while let Ok(evt) = event::read() {
+            let input = match to_input(evt) {
+                Some(input) => input,
+                None        => continue,
+            };
+            if let Err(TrySendError::Disconnected(_)) = sender.try_send(input) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn convert_keys() {
+        assert_eq!(Key::from(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)), Key::Up);
+        assert_eq!(Key::from(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)), Key::Char('q'));
+        assert_eq!(Key::from(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)), Key::Ctrl('c'));
+        assert_eq!(Key::from(KeyEvent::new(KeyCode::F(1), KeyModifiers::NONE)), Key::Other);
+    }
+
+    #[test]
+    fn convert_clicks() {
+        let mouse = |kind| Event::Mouse(MouseEvent { kind, column: 4, row: 2, modifiers: KeyModifiers::NONE });
+        assert_eq!(to_input(mouse(MouseEventKind::Down(MouseButton::Left))), Some(Input::Click(4, 2)));
+        assert_eq!(to_input(mouse(MouseEventKind::Down(MouseButton::Right))), None);
+        assert_eq!(to_input(mouse(MouseEventKind::Moved)), None);
+        assert_eq!(to_input(Event::Resize(80, 24)), Some(Input::Resize(80, 24)));
+    }
+}
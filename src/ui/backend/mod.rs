@@ -0,0 +1,117 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use crossbeam::channel::Sender;
+use tui::backend::Backend;
+
+use super::keymap::Action;
+
+#[cfg(feature = "termion")]
+mod termion_backend;
+#[cfg(feature = "crossterm")]
+mod crossterm_backend;
+#[cfg(feature = "crossterm")]
+pub use crossterm_backend::CrosstermTerminal;
+
+#[cfg(not(any(feature = "termion", feature = "crossterm")))]
+compile_error!("snek needs either the \"termion\" or the \"crossterm\" feature enabled");
+
+// termion is on by default, so asking for crossterm as well means it should
+// be the one used
+#[cfg(feature = "crossterm")]
+pub type DefaultBackend = CrosstermTerminal;
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+pub type DefaultBackend = tui::backend::TermionBackend<termion_backend::MouseReporting<termion::raw::RawTerminal<io::Stdout>>>;
+
+// Key presses, independent of the terminal library that read them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Esc,
+    Char(char),
+    Ctrl(char),
+    Other,
+}
+
+impl Key {
+    // Whether the key can be written to the config file and read back in.
+    // Keys the backend can't tell apart have no name, and whitespace or
+    // control characters would leave nothing after the '='.
+    pub fn can_save(&self) -> bool {
+        match self {
+            Key::Other => return false,
+            Key::Char(' ') => return true,
+            Key::Char(c) | Key::Ctrl(c) => return !c.is_whitespace() && !c.is_control(),
+            _ => return true,
+        }
+    }
+}
+
+// Keys are named in the config file as "up", "down", "left", "right", "esc",
+// "space", "ctrl-<c>", or the character itself
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::Up        => write!(f, "up"),
+            Key::Down      => write!(f, "down"),
+            Key::Left      => write!(f, "left"),
+            Key::Right     => write!(f, "right"),
+            Key::Esc       => write!(f, "esc"),
+            Key::Char(' ') => write!(f, "space"),
+            Key::Char(c)   => write!(f, "{}", c),
+            Key::Ctrl(c)   => write!(f, "ctrl-{}", c),
+            Key::Other     => write!(f, "other"),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up"    => return Ok(Key::Up),
+            "down"  => return Ok(Key::Down),
+            "left"  => return Ok(Key::Left),
+            "right" => return Ok(Key::Right),
+            "esc"   => return Ok(Key::Esc),
+            "space" => return Ok(Key::Char(' ')),
+            _ => {},
+        }
+        let single = |s: &str| {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        };
+        if let Some(c) = s.strip_prefix("ctrl-").and_then(single) {
+            return Ok(Key::Ctrl(c));
+        }
+        return single(s).map(Key::Char).ok_or_else(|| format!("unknown key '{}'", s));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Key(Key),
+    Resize(u16, u16),
+    // the left mouse button pressed at this column and row, counted from 0
+    // at the top left of the terminal
+    Click(u16, u16),
+    // an action asked for by something other than the terminal
+    Command(Action),
+}
+
+// A tui backend that can take over the real terminal and read its input
+pub trait TerminalBackend: Backend + Sized {
+    // put the terminal into raw mode and hand back a backend drawing to it,
+    // asking the terminal to report mouse clicks if `mouse` is set
+    fn open(mouse: bool) -> Result<Self, io::Error>;
+
+    // block reading input events and pass them on, returning once the
+    // receiving end has gone away. This is run on its own thread.
+    fn read_input(sender: Sender<Input>);
+}
@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+use crossbeam::channel::{Sender, SendTimeoutError, TrySendError};
+use termion::event::{self, Event, MouseButton, MouseEvent};
+use termion::input::TermRead;
+use termion::raw::{IntoRawMode, RawTerminal};
+use tui::backend::TermionBackend;
+
+use super::{Input, Key, TerminalBackend};
+
+impl From<event::Key> for Key {
+    fn from(key: event::Key) -> Self {
+        match key {
+            event::Key::Up      => Key::Up,
+            event::Key::Down    => Key::Down,
+            event::Key::Left    => Key::Left,
+            event::Key::Right   => Key::Right,
+            event::Key::Esc     => Key::Esc,
+            event::Key::Char(c) => Key::Char(c),
+            event::Key::Ctrl(c) => Key::Ctrl(c),
+            _                   => Key::Other,
+        }
+    }
+}
+
+// the same as termion's MouseTerminal, which can't be turned off
+const ENTER_MOUSE_SEQUENCE: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h";
+const EXIT_MOUSE_SEQUENCE: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+
+// A terminal that reports mouse events while it's open, if asked to
+pub struct MouseReporting<W: Write> {
+    term: W,
+    on: bool,
+}
+
+impl<W: Write> MouseReporting<W> {
+    fn new(mut term: W, on: bool) -> Result<Self, io::Error> {
+        if on {
+            term.write_all(ENTER_MOUSE_SEQUENCE.as_bytes())?;
+            term.flush()?;
+        }
+        return Ok(Self { term, on });
+    }
+}
+
+impl<W: Write> Drop for MouseReporting<W> {
+    fn drop(&mut self) {
+        if self.on {
+            let _ = self.term.write_all(EXIT_MOUSE_SEQUENCE.as_bytes());
+            let _ = self.term.flush();
+        }
+    }
+}
+
+impl<W: Write> Write for MouseReporting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.term.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.term.flush()
+    }
+}
+
+// termion counts from 1
+fn to_input(evt: Event) -> Option<Input> {
+    match evt {
+        Event::Key(key) => Some(Input::Key(key.into())),
+        Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => Some(Input::Click(x.saturating_sub(1), y.saturating_sub(1))),
+        _ => None,
+    }
+}
+
+impl TerminalBackend for TermionBackend<MouseReporting<RawTerminal<io::Stdout>>> {
+    fn open(mouse: bool) -> Result<Self, io::Error> {
+        let stdout = io::stdout().into_raw_mode()?;
+        return Ok(TermionBackend::new(MouseReporting::new(stdout, mouse)?));
+    }
+
+    fn read_input(sender: Sender<Input>) {
+        // termion has no resize events, so watch the size on another thread
+        let resize_tx = sender.clone();
+        thread::spawn(move || {
+            let mut last_size = termion::terminal_size().ok();
+            loop {
+                thread::sleep(Duration::from_millis(250));
+                let size = termion::terminal_size().ok();
+                if size != last_size {
+                    last_size = size;
+                    if let Some((w,h)) = size {
+                        // a resize matters more than a key press, so wait for room
+                        let input = Input::Resize(w,h);
+                        if let Err(SendTimeoutError::Disconnected(_)) = resize_tx.send_timeout(input, Duration::from_millis(250)) {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        let stdin = io::stdin();
+        for input in stdin.events().flatten().filter_map(to_input) {
+            // if we have a key or click, then try to send it
+            if let Err(TrySendError::Disconnected(_)) = sender.try_send(input) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn convert_keys() {
+        assert_eq!(Key::from(event::Key::Up), Key::Up);
+        assert_eq!(Key::from(event::Key::Char('q')), Key::Char('q'));
+        assert_eq!(Key::from(event::Key::Ctrl('c')), Key::Ctrl('c'));
+        assert_eq!(Key::from(event::Key::F(1)), Key::Other);
+    }
+
+    #[test]
+    fn convert_clicks() {
+        assert_eq!(to_input(Event::Mouse(MouseEvent::Press(MouseButton::Left, 1, 1))), Some(Input::Click(0, 0)));
+        assert_eq!(to_input(Event::Mouse(MouseEvent::Press(MouseButton::Right, 5, 3))), None);
+        assert_eq!(to_input(Event::Mouse(MouseEvent::Release(5, 3))), None);
+        assert_eq!(to_input(Event::Key(event::Key::Esc)), Some(Input::Key(Key::Esc)));
+    }
+}
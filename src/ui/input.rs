@@ -0,0 +1,164 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::marker::PhantomData;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use crossbeam::channel::{bounded, Receiver, Sender};
+
+use super::backend::{Input, TerminalBackend};
+use super::keymap::Action;
+
+// Somewhere input comes from. Each source is run on its own thread, passing
+// what it reads on, and should stop once nothing is listening any more.
+pub trait InputSource: Send {
+    fn run(self: Box<Self>, sender: Sender<Input>);
+}
+
+// Start every source on its own thread, all feeding the one channel
+pub fn start(sources: Vec<Box<dyn InputSource>>) -> Receiver<Input> {
+    let (sender, receiver) = bounded::<Input>(1);
+    for source in sources {
+        let tx = sender.clone();
+        thread::spawn(move || source.run(tx));
+    }
+    return receiver;
+}
+
+// The keys and mouse of the terminal the game is drawn in
+pub struct Terminal<B> {
+    backend: PhantomData<fn() -> B>,
+}
+
+impl<B> Terminal<B> {
+    pub(crate) fn new() -> Self {
+        return Self { backend: PhantomData };
+    }
+}
+
+impl<B: TerminalBackend> InputSource for Terminal<B> {
+    fn run(self: Box<Self>, sender: Sender<Input>) {
+        B::read_input(sender);
+    }
+}
+
+// Pass on one command a line, each naming an action the way the config file
+// does: "up", "down-left", "faster", "quit" and so on. Anything else is
+// skipped. Returns false once nothing is listening.
+fn read_commands<R: BufRead>(reader: R, sender: &Sender<Input>) -> bool {
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_)   => break,
+        };
+        if let Ok(action) = Action::from_str(line.trim()) {
+            // unlike key presses, commands wait their turn rather than
+            // being dropped, so a script can send several at once
+            if sender.send(Input::Command(action)).is_err() {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+// A named pipe, made beforehand with mkfifo. Writers can come and go, as the
+// pipe is opened again after each one is done with it.
+pub struct Fifo {
+    path: PathBuf,
+}
+
+impl Fifo {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref();
+        if !fs::metadata(path)?.file_type().is_fifo() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a named pipe, make one with mkfifo"));
+        }
+        return Ok(Self { path: path.to_path_buf() });
+    }
+}
+
+impl InputSource for Fifo {
+    fn run(self: Box<Self>, sender: Sender<Input>) {
+        // opening blocks until something opens the pipe to write
+        while let Ok(file) = File::open(&self.path) {
+            if !read_commands(BufReader::new(file), &sender) {
+                return;
+            }
+        }
+    }
+}
+
+// A Unix socket taking commands over any number of connections at once. The
+// socket file is left behind when the game ends, and cleared away by the
+// next game to use it.
+pub struct Socket {
+    listener: UnixListener,
+}
+
+impl Socket {
+    pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref();
+        if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            fs::remove_file(path)?;
+        }
+        return Ok(Self { listener: UnixListener::bind(path)? });
+    }
+}
+
+impl InputSource for Socket {
+    fn run(self: Box<Self>, sender: Sender<Input>) {
+        for stream in self.listener.incoming().flatten() {
+            let tx = sender.clone();
+            thread::spawn(move || read_commands(BufReader::new(stream), &tx));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    #[test]
+    fn commands_from_lines() {
+        let (sender, receiver) = bounded::<Input>(4);
+        assert!(read_commands(Cursor::new("up\n  down-left \nsideways\n\nquit\n"), &sender));
+        let commands: Vec<Input> = receiver.try_iter().collect();
+        assert_eq!(commands, vec![
+            Input::Command(Action::Up),
+            Input::Command(Action::DownLeft),
+            Input::Command(Action::Quit),
+        ]);
+        drop(receiver);
+        assert!(!read_commands(Cursor::new("up\n"), &sender));
+    }
+
+    #[test]
+    fn commands_over_a_socket() {
+        let dir = std::env::temp_dir().join(format!("snek-socket-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snek.sock");
+        // a socket left over from before is replaced
+        drop(Socket::bind(&path).unwrap());
+        let socket = Socket::bind(&path).unwrap();
+
+        let receiver = start(vec![Box::new(socket)]);
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream.write_all(b"left\nfaster\n").unwrap();
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout), Ok(Input::Command(Action::Left)));
+        assert_eq!(receiver.recv_timeout(timeout), Ok(Input::Command(Action::Faster)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fifo_must_be_a_pipe() {
+        assert!(Fifo::open("/this/path/does/not/exist").is_err());
+        assert!(Fifo::open(std::env::current_exe().unwrap()).is_err());
+    }
+}
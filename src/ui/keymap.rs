@@ -0,0 +1,240 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::game::SnakeControl;
+use super::backend::Key;
+use super::UIControl;
+
+// Something a key can be bound to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    Faster,
+    Slower,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::Up, Action::Down, Action::Left, Action::Right,
+        Action::UpLeft, Action::UpRight, Action::DownLeft, Action::DownRight,
+        Action::Faster, Action::Slower, Action::Quit,
+    ];
+
+    pub fn control(&self) -> UIControl {
+        let control = match self {
+            Action::Up        => SnakeControl::Up,
+            Action::Down      => SnakeControl::Down,
+            Action::Left      => SnakeControl::Left,
+            Action::Right     => SnakeControl::Right,
+            Action::UpLeft    => SnakeControl::UpLeft,
+            Action::UpRight   => SnakeControl::UpRight,
+            Action::DownLeft  => SnakeControl::DownLeft,
+            Action::DownRight => SnakeControl::DownRight,
+            Action::Faster    => SnakeControl::Faster,
+            Action::Slower    => SnakeControl::Slower,
+            Action::Quit      => return UIControl::ExitProgram,
+        };
+        return UIControl::SnakeControl(control);
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Action::Up        => "up",
+            Action::Down      => "down",
+            Action::Left      => "left",
+            Action::Right     => "right",
+            Action::UpLeft    => "up-left",
+            Action::UpRight   => "up-right",
+            Action::DownLeft  => "down-left",
+            Action::DownRight => "down-right",
+            Action::Faster    => "faster",
+            Action::Slower    => "slower",
+            Action::Quit      => "quit",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL.iter().find(|a| a.to_string() == s).copied().ok_or_else(|| format!("unknown action '{}'", s))
+    }
+}
+
+// Which key does what. Each key does at most one thing, but an action can
+// have several keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+// in every preset
+const COMMON: [(Key, Action); 6] = [
+    (Key::Up, Action::Up),
+    (Key::Down, Action::Down),
+    (Key::Left, Action::Left),
+    (Key::Right, Action::Right),
+    (Key::Esc, Action::Quit),
+    (Key::Ctrl('c'), Action::Quit),
+];
+
+impl Keymap {
+    pub const PRESETS: [&'static str; 4] = ["arrows", "vim", "wasd", "numpad"];
+
+    pub fn preset(name: &str) -> Result<Self, String> {
+        // a key for each of the actions below, in order; the diagonals sit
+        // round the middle key of each cluster
+        let letters = match name {
+            "arrows" => "weaxzd+-q",
+            "vim"    => "kjhlyubn+-q",
+            "wasd"   => "wsadqezc+-",
+            "numpad" => "82467913+-q",
+            _ => return Err(format!("unknown keymap '{}', expected one of: {}", name, Self::PRESETS.join(", "))),
+        };
+        let mut keymap = Self { bindings: COMMON.to_vec() };
+        // the arrows preset leaves the arrow keys for up, down, left and right
+        let actions = if name == "arrows" {
+            vec![Action::UpLeft, Action::UpRight, Action::Left, Action::DownRight, Action::DownLeft, Action::Right,
+                 Action::Faster, Action::Slower, Action::Quit]
+        } else {
+            Action::ALL.to_vec()
+        };
+        for (c, action) in letters.chars().zip(actions) {
+            keymap.bindings.push((Key::Char(c), action));
+        }
+        keymap.bindings.push((Key::Char('='), Action::Faster));
+        return Ok(keymap);
+    }
+
+    // Player one's keys come from `keys = <preset>` and `key.<action> = KEY
+    // [KEY...]` in the config, other players' from the same keys under
+    // `player<N>.`
+    pub fn from_config(config: &Config, player: usize) -> Result<Self, String> {
+        let prefix = Self::config_prefix(player);
+        let mut keymap = Self::preset(config.get(&format!("{}keys", prefix)).unwrap_or("arrows"))?;
+        for action in Action::ALL.iter() {
+            if let Some(keys) = config.get(&format!("{}key.{}", prefix, action)) {
+                let keys = keys.split_whitespace().map(Key::from_str).collect::<Result<Vec<Key>,_>>()?;
+                keymap.bind(*action, &keys);
+            }
+        }
+        return Ok(keymap);
+    }
+
+    pub fn config_prefix(player: usize) -> String {
+        if player <= 1 {
+            return String::new();
+        }
+        return format!("player{}.", player);
+    }
+
+    // give `action` exactly these keys, taking them off anything else
+    pub fn bind(&mut self, action: Action, keys: &[Key]) {
+        self.bindings.retain(|(k, a)| *a != action && !keys.contains(k));
+        self.bindings.extend(keys.iter().map(|k| (*k, action)));
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        return self.bindings.iter().find(|(k, _)| *k == key).map(|(_, a)| *a);
+    }
+
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        return self.bindings.iter().filter(|(_, a)| *a == action).map(|(k, _)| *k).collect();
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        return Self::preset("arrows").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn key_names() {
+        for key in [Key::Up, Key::Esc, Key::Char('k'), Key::Char(' '), Key::Ctrl('c')].iter() {
+            assert_eq!(Key::from_str(&key.to_string()), Ok(*key));
+        }
+        assert!(Key::from_str("kk").is_err());
+        assert!(Key::from_str("ctrl-").is_err());
+    }
+
+    #[test]
+    fn saved_keys_read_back() {
+        let mut keys = vec![Key::Up, Key::Down, Key::Left, Key::Right, Key::Esc, Key::Other];
+        for c in (0..0x300).chain(0x2000..0x2100).filter_map(std::char::from_u32) {
+            keys.push(Key::Char(c));
+            keys.push(Key::Ctrl(c));
+        }
+        for key in keys.iter().filter(|k| k.can_save()) {
+            let line = format!("key.up = {}\n", key);
+            let config = Config::parse(&line);
+            let read = config.get("key.up").map(Key::from_str);
+            assert_eq!(read, Some(Ok(*key)), "{:?}", key);
+        }
+        for key in [Key::Other, Key::Char('\n'), Key::Char('\t'), Key::Ctrl('\r')].iter() {
+            assert!(!key.can_save(), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn presets() {
+        for name in Keymap::PRESETS.iter() {
+            let keymap = Keymap::preset(name).unwrap();
+            // everything can be done, and there's always a way out
+            for action in Action::ALL.iter() {
+                assert!(!keymap.keys(*action).is_empty(), "{} has no key for {}", name, action);
+            }
+            assert_eq!(keymap.action(Key::Esc), Some(Action::Quit));
+        }
+        assert_eq!(Keymap::default().action(Key::Char('x')), Some(Action::DownRight));
+        assert_eq!(Keymap::preset("vim").unwrap().action(Key::Char('k')), Some(Action::Up));
+        assert_eq!(Keymap::preset("wasd").unwrap().action(Key::Char('q')), Some(Action::UpLeft));
+        assert_eq!(Keymap::preset("numpad").unwrap().action(Key::Char('3')), Some(Action::DownRight));
+        assert!(Keymap::preset("dvorak").is_err());
+    }
+
+    #[test]
+    fn keymaps_from_config() {
+        let config = Config::parse("keys = vim\nkey.up = w k\nplayer2.keys = wasd\nplayer2.key.quit = p\n");
+        let one = Keymap::from_config(&config, 1).unwrap();
+        assert_eq!(one.keys(Action::Up), vec![Key::Char('w'), Key::Char('k')]);
+        assert_eq!(one.action(Key::Up), None);
+        assert_eq!(one.action(Key::Char('j')), Some(Action::Down));
+
+        let two = Keymap::from_config(&config, 2).unwrap();
+        assert_eq!(two.action(Key::Char('w')), Some(Action::Up));
+        assert_eq!(two.keys(Action::Quit), vec![Key::Char('p')]);
+
+        assert!(Keymap::from_config(&Config::parse("key.up = page-up\n"), 1).is_err());
+    }
+
+    #[test]
+    fn other_players_fall_back_to_their_preset() {
+        let config = Config::parse("keys = vim\nkey.quit = p\nplayer2.key.up = i\n");
+        let two = Keymap::from_config(&config, 2).unwrap();
+        let arrows = Keymap::preset("arrows").unwrap();
+        // player 2's own binding, and nothing of player 1's
+        assert_eq!(two.keys(Action::Up), vec![Key::Char('i')]);
+        assert_eq!(two.action(Key::Char('p')), None);
+        assert_eq!(two.action(Key::Char('j')), None);
+        for action in Action::ALL.iter().filter(|a| **a != Action::Up) {
+            assert_eq!(two.keys(*action), arrows.keys(*action), "{}", action);
+        }
+    }
+}
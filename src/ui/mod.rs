@@ -0,0 +1,488 @@
+use std::io;
+use tui::Terminal;
+use tui::backend::Backend;
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::widgets::{Block, Borders, Paragraph, Widget};
+use tui::text::Spans;
+
+use crate::game::{RenderData, SnakeControl, SPEED_UNIT};
+
+mod backend;
+pub use backend::{DefaultBackend, TerminalBackend, Input, Key};
+
+mod theme;
+pub use theme::Theme;
+mod render;
+pub use render::RenderMode;
+mod keymap;
+pub use keymap::{Keymap, Action};
+pub mod input;
+use input::InputSource;
+
+
+#[derive(PartialEq, Debug)]
+pub enum UIControl {
+    SnakeControl(SnakeControl),
+    Resize,
+    ExitProgram
+}
+
+impl UIControl {
+    pub fn get_snake_control(self) -> SnakeControl {
+        match self {
+            UIControl::SnakeControl(c) => c,
+            _ => SnakeControl::None,
+        }
+    }
+}
+
+pub struct UI<B: Backend = DefaultBackend> {
+    terminal: Terminal<B>,
+    theme: Theme,
+    mode: RenderMode,
+    keymap: Keymap,
+    // the width and height of the board last drawn, and whether its cells
+    // are hexagons, so a click can be matched to a cell
+    board: Option<((usize, usize), bool)>,
+    // the screen row of each menu item showing, and the key it stands for
+    menu: Vec<(u16, Key)>,
+    // what's on the screen, so only what differs is sent to the terminal
+    screen: Buffer,
+    // how the board was last drawn, if it's still showing, so the next
+    // frame need only draw the rows that have changed
+    drawn: Option<Drawn>,
+}
+
+struct Drawn {
+    theme: Theme,
+    planned: Option<[usize;2]>,
+}
+
+impl<B: TerminalBackend + 'static> UI<B> {
+    // `mouse` turns on clicking, in terminals that report the mouse
+    pub fn new(theme: Theme, mode: RenderMode, mouse: bool) -> Result<Self, io::Error> {
+        let backend  = B::open(mouse)?;
+        return Self::with_backend(backend, theme, mode);
+    }
+
+    // The keys and clicks of the terminal the UI draws in, to be read
+    // alongside any other sources once the UI is open
+    pub fn terminal_input() -> Box<dyn InputSource> {
+        return Box::new(input::Terminal::<B>::new());
+    }
+}
+
+impl<B: Backend> UI<B> {
+    // Build a UI over any backend. Used for testing against tui's
+    // TestBackend.
+    pub fn with_backend(backend: B, theme: Theme, mode: RenderMode) -> Result<Self, io::Error> {
+        let mut terminal = Terminal::new(backend)?;
+        terminal.hide_cursor()?;
+        let screen = Buffer::empty(terminal.size()?);
+        Ok(Self {
+            terminal,
+            theme,
+            mode,
+            keymap: Keymap::default(),
+            board: None,
+            menu: Vec::new(),
+            screen,
+            drawn: None,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.terminal.clear().unwrap();
+        self.screen.reset();
+        self.drawn = None;
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    // what a piece of input means for the game
+    pub fn control(&self, input: Input) -> UIControl {
+        match input {
+            // however the keys are bound, ctrl-c always gets out
+            Input::Key(Key::Ctrl('c')) => UIControl::ExitProgram,
+            Input::Key(key) => match self.keymap.action(key) {
+                Some(action) => action.control(),
+                None         => UIControl::SnakeControl(SnakeControl::None),
+            },
+            Input::Resize(_,_) => UIControl::Resize,
+            Input::Command(action) => action.control(),
+            Input::Click(col, row) => match self.clicked_cell(col, row) {
+                Some(cell) => UIControl::SnakeControl(SnakeControl::Goto(cell)),
+                None       => UIControl::SnakeControl(SnakeControl::None),
+            },
+        }
+    }
+
+    // the board cell under a click, if it landed on the board
+    fn clicked_cell(&self, col: u16, row: u16) -> Option<[usize;2]> {
+        let (size, hex) = self.board?;
+        // the board starts inside the border
+        let (col, row) = (col.checked_sub(1)?, row.checked_sub(1)?);
+        return render::cell_at(size, hex, self.mode, col as usize, row as usize);
+    }
+
+    // Draw the board. When the render data says which cells have changed
+    // since the last frame, only the rows holding them are drawn again,
+    // unless something else about the board has changed too.
+    pub fn render(&mut self,rd: RenderData) {
+        let theme = render::effect_theme(&self.theme, &rd.effects);
+        let height = rd.rm.first().map_or(0, |column| column.len());
+        let board = Some(((rd.rm.len(), height), rd.hex));
+        let rows = match (&self.drawn, &rd.changed) {
+            (Some(drawn), Some(changed)) if self.board == board && drawn.theme == theme && self.terminal.size().ok() == Some(self.screen.area) => {
+                // the marked cell the head is heading for moves without
+                // anything changing on the board
+                let mut cells = changed.clone();
+                cells.extend(drawn.planned);
+                cells.extend(rd.planned);
+                Some(render::rows_touched((rd.rm.len(), height), rd.hex, self.mode, &cells))
+            },
+            _ => None,
+        };
+        self.board = board;
+        self.menu.clear();
+
+        let mut hud = format!("score: {}", rd.score);
+        if rd.combo > 1 {
+            hud = format!("{} combo:{}", hud, rd.combo);
+        }
+        if rd.speed != SPEED_UNIT {
+            hud = format!("{} speed:{}.{:02}", hud, rd.speed/SPEED_UNIT, rd.speed%SPEED_UNIT*100/SPEED_UNIT);
+        }
+        if let Some(status) = &rd.status {
+            hud = format!("{} {}", hud, status);
+        }
+        if !rd.effects.is_empty() {
+            hud = format!("{} {}", hud, render::effect_summary(&rd.effects));
+        }
+        match rows {
+            Some(rows) => {
+                let text = render::render_rows(&rd, &theme, self.mode, &rows);
+                // below the board, inside the border
+                let hud_row = render::board_rows((rd.rm.len(), height), rd.hex, self.mode);
+                let mut lines: Vec<(usize, Spans)> = rows.into_iter().zip(text).collect();
+                lines.push((hud_row, Spans::from(hud)));
+                self.patch(lines);
+            },
+            None => {
+                let mut text = render::render_board(&rd, &theme, self.mode);
+                text.push(Spans::from(hud));
+                self.draw(text);
+            },
+        }
+        self.drawn = Some(Drawn { theme, planned: rd.planned });
+    }
+
+    // Draw the whole frame: the lines of text inside the border
+    fn draw(&mut self, text: Vec<Spans>) {
+        let area = self.terminal.size().unwrap();
+        if area != self.screen.area {
+            self.terminal.clear().unwrap();
+            self.screen = Buffer::empty(area);
+        }
+        let mut next = Buffer::empty(area);
+        let block = Block::default()
+            .title("Snek")
+            .borders(Borders::ALL);
+        Paragraph::new(text).block(block).render(area, &mut next);
+        let updates = self.screen.diff(&next);
+        let backend = self.terminal.backend_mut();
+        backend.draw(updates.into_iter()).unwrap();
+        backend.flush().unwrap();
+        self.screen = next;
+    }
+
+    // Draw some lines of the frame and leave the rest as it is, each line
+    // given by its row inside the border. Lines that don't fit are dropped,
+    // as they are from the whole frame.
+    fn patch(&mut self, lines: Vec<(usize, Spans)>) {
+        let area = self.screen.area;
+        let width = area.width.saturating_sub(2);
+        let mut updates = Vec::new();
+        for (row, spans) in lines {
+            if row+2 >= area.height as usize || width == 0 {
+                continue;
+            }
+            let y = 1+row as u16;
+            let mut line = Buffer::empty(Rect::new(1, y, width, 1));
+            line.set_spans(1, y, &spans, width);
+            for (x, cell) in (1..).zip(line.content) {
+                let showing = self.screen.get_mut(x, y);
+                if *showing != cell {
+                    *showing = cell.clone();
+                    updates.push((x, y, cell));
+                }
+            }
+        }
+        if updates.is_empty() {
+            return;
+        }
+        let backend = self.terminal.backend_mut();
+        backend.draw(updates.iter().map(|(x, y, cell)| (*x, *y, cell))).unwrap();
+        backend.flush().unwrap();
+    }
+
+    // shown in place of the board once the game is won
+    pub fn victory(&mut self, score: usize, perfect: bool) {
+        let mut lines = vec!["You win!".to_string()];
+        if perfect {
+            lines.push("Perfect game!".to_string());
+        }
+        lines.push(format!("score: {}", score));
+        self.menu(lines, vec![(Key::Char(' '), "any key...".to_string())]);
+    }
+
+    // Lines of text in place of the board, followed by a line for each item,
+    // which a click turns into the item's key
+    pub fn menu(&mut self, lines: Vec<String>, items: Vec<(Key, String)>) {
+        self.board = None;
+        self.drawn = None;
+        // below the lines, inside the border
+        self.menu = items.iter().enumerate().map(|(index, (key, _))| ((1+lines.len()+index) as u16, *key)).collect();
+        let text: Vec<Spans> = lines.into_iter().chain(items.into_iter().map(|(_, item)| item)).map(Spans::from).collect();
+        self.draw(text);
+    }
+
+    // The key pressed, if the input was one, counting a click on a menu
+    // item as its key. Resizes and commands don't count.
+    pub fn menu_key(&self, input: Input) -> Option<Key> {
+        match input {
+            Input::Key(key) => Some(key),
+            Input::Click(_, row) => self.menu.iter().find(|(r, _)| *r == row).map(|(_, key)| *key),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tui::backend::TestBackend;
+    use tui::buffer::Buffer;
+    use tui::layout::Rect;
+    use tui::style::Modifier;
+    use std::rc::Rc;
+    use crate::game::{Item, FoodGroup, PowerUp, Effect};
+
+    fn test_ui(theme: Theme, mode: RenderMode) -> UI<TestBackend> {
+        return UI::with_backend(TestBackend::new(12, 5), theme, mode).unwrap();
+    }
+
+    // 3x2 board holding one of every item
+    fn test_data() -> RenderData {
+        let mut rm = vec![vec![Item::Nothing; 2]; 3];
+        rm[0][1] = Item::SnakeHead;
+        rm[1][1] = Item::Snake;
+        rm[0][0] = Item::Food(FoodGroup::Grow);
+        rm[1][0] = Item::Food(FoodGroup::Shrink);
+        rm[2][0] = Item::Food(FoodGroup::Poison);
+        RenderData {
+            rm: Rc::new(rm),
+            changed: None,
+            snake: vec![[0,1],[1,1]],
+            score: 7,
+            combo: 0,
+            speed: SPEED_UNIT,
+            status: None,
+            effects: Vec::new(),
+            hex: false,
+            planned: None,
+        }
+    }
+
+    // style the two characters making up board cell x,y (y counted from
+    // the top of the screen)
+    fn style_cell(buffer: &mut Buffer, x: u16, y: u16, glyph: theme::Glyph) {
+        buffer.set_style(Rect::new(1+2*x, 1+y, 2, 1), glyph.style);
+    }
+
+    #[test]
+    fn render_ascii_snapshot() {
+        let theme = Theme::default();
+        let mut ui = test_ui(theme.clone(), RenderMode::Ascii);
+        ui.render(test_data());
+
+        let mut expected = Buffer::with_lines(vec![
+            "┌Snek──────┐",
+            "│ O * -    │",
+            "│ o v x    │",
+            "│score: 7  │",
+            "└──────────┘",
+        ]);
+        style_cell(&mut expected, 0, 0, theme.snake_head);
+        style_cell(&mut expected, 1, 0, theme.snake);
+        style_cell(&mut expected, 2, 0, theme.nothing);
+        style_cell(&mut expected, 0, 1, theme.grow);
+        style_cell(&mut expected, 1, 1, theme.shrink);
+        style_cell(&mut expected, 2, 1, theme.poison);
+        ui.terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn render_unicode_snapshot() {
+        let theme = Theme::monochrome();
+        let mut ui = test_ui(theme.clone(), RenderMode::Unicode);
+        ui.render(test_data());
+
+        let mut expected = Buffer::with_lines(vec![
+            "┌Snek──────┐",
+            "│ ●─╴ .    │",
+            "│ o v x    │",
+            "│score: 7  │",
+            "└──────────┘",
+        ]);
+        style_cell(&mut expected, 0, 0, theme.snake_head);
+        ui.terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn render_effects_hud() {
+        let theme = Theme::default();
+        let mut ui = test_ui(theme.clone(), RenderMode::Ascii);
+        let mut rd = test_data();
+        Rc::make_mut(&mut rd.rm)[2][1] = Item::Food(FoodGroup::PowerUp(PowerUp::Magnet));
+        rd.effects = vec![Effect { kind: PowerUp::Ghost, remaining: 4, level: 1 }];
+        ui.render(rd);
+
+        let mut expected = Buffer::with_lines(vec![
+            "┌Snek──────┐",
+            "│ O * M    │",
+            "│ o v x    │",
+            "│score: 7 g│",
+            "└──────────┘",
+        ]);
+        let dim = |g: theme::Glyph| theme::Glyph { style: g.style.add_modifier(Modifier::DIM), ..g };
+        style_cell(&mut expected, 0, 0, dim(theme.snake_head));
+        style_cell(&mut expected, 1, 0, dim(theme.snake));
+        style_cell(&mut expected, 2, 0, theme.food(FoodGroup::PowerUp(PowerUp::Magnet)));
+        style_cell(&mut expected, 0, 1, theme.grow);
+        style_cell(&mut expected, 1, 1, theme.shrink);
+        style_cell(&mut expected, 2, 1, theme.poison);
+        ui.terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn redraw_changed_rows() {
+        for mode in [RenderMode::Ascii, RenderMode::Unicode, RenderMode::HalfBlock].iter() {
+            let mut ui = test_ui(Theme::default(), *mode);
+            ui.render(test_data());
+            let changed = || {
+                let mut rd = test_data();
+                Rc::make_mut(&mut rd.rm)[2][1] = Item::Food(FoodGroup::Grow);
+                rd.changed = Some(vec![[2,1]]);
+                rd.score = 8;
+                rd
+            };
+            ui.render(changed());
+            // just as if the whole board were drawn again
+            let mut fresh = test_ui(Theme::default(), *mode);
+            fresh.render(changed());
+            assert_eq!(ui.terminal.backend().buffer(), fresh.terminal.backend().buffer());
+        }
+
+        // only the rows with changes are drawn
+        let mut ui = test_ui(Theme::monochrome(), RenderMode::Ascii);
+        ui.render(test_data());
+        let mut rd = test_data();
+        Rc::make_mut(&mut rd.rm)[0][0] = Item::Nothing;
+        Rc::make_mut(&mut rd.rm)[2][1] = Item::Food(FoodGroup::Grow);
+        rd.changed = Some(vec![[2,1]]);
+        ui.render(rd);
+        let text: String = ui.terminal.backend().buffer().content[12..36].iter().map(|c| c.symbol.as_str()).collect();
+        // the cell left out of the changes is as it was
+        assert_eq!(text, "│ O * o    ││ o v x    │");
+    }
+
+    #[test]
+    fn render_speed_hud() {
+        let mut ui = UI::with_backend(TestBackend::new(24, 5), Theme::monochrome(), RenderMode::Ascii).unwrap();
+        let mut rd = test_data();
+        rd.speed = SPEED_UNIT+SPEED_UNIT/4;
+        ui.render(rd);
+        let hud: String = ui.terminal.backend().buffer().content[3*24..4*24].iter().map(|c| c.symbol.as_str()).collect();
+        assert_eq!(hud, "│score: 7 speed:1.25   │");
+    }
+
+    #[test]
+    fn injected_input() {
+        let ui = test_ui(Theme::default(), RenderMode::Ascii);
+        assert_eq!(ui.control(Input::Key(Key::Up)), UIControl::SnakeControl(SnakeControl::Up));
+        assert_eq!(ui.control(Input::Key(Key::Other)), UIControl::SnakeControl(SnakeControl::None));
+        assert_eq!(ui.control(Input::Resize(80, 24)), UIControl::Resize);
+        assert_eq!(ui.control(Input::Key(Key::Char('+'))), UIControl::SnakeControl(SnakeControl::Faster));
+        assert_eq!(ui.control(Input::Key(Key::Char('-'))), UIControl::SnakeControl(SnakeControl::Slower));
+        assert_eq!(ui.control(Input::Key(Key::Char('x'))), UIControl::SnakeControl(SnakeControl::DownRight));
+        assert_eq!(ui.control(Input::Key(Key::Char('q'))), UIControl::ExitProgram);
+        // commands from elsewhere don't go through the keymap
+        assert_eq!(ui.control(Input::Command(Action::UpLeft)), UIControl::SnakeControl(SnakeControl::UpLeft));
+    }
+
+    #[test]
+    fn rebound_keys() {
+        let mut ui = test_ui(Theme::default(), RenderMode::Ascii);
+        let mut keymap = Keymap::preset("vim").unwrap();
+        keymap.bind(Action::Quit, &[Key::Char('x')]);
+        ui.set_keymap(keymap);
+        assert_eq!(ui.control(Input::Key(Key::Char('k'))), UIControl::SnakeControl(SnakeControl::Up));
+        assert_eq!(ui.control(Input::Key(Key::Char('x'))), UIControl::ExitProgram);
+        assert_eq!(ui.control(Input::Key(Key::Char('q'))), UIControl::SnakeControl(SnakeControl::None));
+        // ctrl-c gets out whatever the keymap says
+        assert_eq!(ui.control(Input::Key(Key::Ctrl('c'))), UIControl::ExitProgram);
+    }
+
+    #[test]
+    fn click_on_board() {
+        let mut ui = test_ui(Theme::default(), RenderMode::Ascii);
+        // nothing drawn yet, so nothing to click on
+        assert_eq!(ui.control(Input::Click(1, 1)), UIControl::SnakeControl(SnakeControl::None));
+
+        ui.render(test_data());
+        assert_eq!(ui.control(Input::Click(1, 1)), UIControl::SnakeControl(SnakeControl::Goto([0,1])));
+        assert_eq!(ui.control(Input::Click(6, 2)), UIControl::SnakeControl(SnakeControl::Goto([2,0])));
+        // the border and the score aren't the board
+        assert_eq!(ui.control(Input::Click(0, 1)), UIControl::SnakeControl(SnakeControl::None));
+        assert_eq!(ui.control(Input::Click(1, 3)), UIControl::SnakeControl(SnakeControl::None));
+    }
+
+    #[test]
+    fn click_on_menu() {
+        let mut ui = test_ui(Theme::default(), RenderMode::Ascii);
+        ui.menu(vec!["Pick one".to_string()], vec![(Key::Char('a'), "apple".to_string()), (Key::Esc, "none".to_string())]);
+        ui.terminal.backend().assert_buffer(&Buffer::with_lines(vec![
+            "┌Snek──────┐",
+            "│Pick one  │",
+            "│apple     │",
+            "│none      │",
+            "└──────────┘",
+        ]));
+        assert_eq!(ui.menu_key(Input::Click(3, 3)), Some(Key::Esc));
+        assert_eq!(ui.menu_key(Input::Click(3, 2)), Some(Key::Char('a')));
+        assert_eq!(ui.menu_key(Input::Click(3, 1)), None);
+        // a board click doesn't steer while the menu is up
+        assert_eq!(ui.control(Input::Click(1, 1)), UIControl::SnakeControl(SnakeControl::None));
+    }
+
+    #[test]
+    fn render_victory() {
+        let mut ui = test_ui(Theme::default(), RenderMode::Ascii);
+        ui.victory(21, true);
+        ui.terminal.backend().assert_buffer(&Buffer::with_lines(vec![
+            "┌Snek──────┐",
+            "│You win!  │",
+            "│Perfect ga│",
+            "│score: 21 │",
+            "└──────────┘",
+        ]));
+
+        assert_eq!(ui.menu_key(Input::Resize(80, 24)), None);
+        assert_eq!(ui.menu_key(Input::Command(Action::Quit)), None);
+        assert_eq!(ui.menu_key(Input::Key(Key::Char('n'))), Some(Key::Char('n')));
+    }
+}
@@ -0,0 +1,523 @@
+use std::str::FromStr;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+
+use crate::game::{Item, FoodGroup, RenderData, Effect, PowerUp, CritterKind};
+use super::theme::Theme;
+
+// How the board is turned into terminal characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderMode {
+    // two ASCII characters per cell
+    #[default]
+    Ascii,
+    // two characters per cell, with the snake drawn as connected line segments
+    Unicode,
+    // one character per two cells, stacked vertically
+    HalfBlock,
+    // one braille character per 2x4 block of cells
+    Braille,
+}
+
+impl RenderMode {
+    pub const NAMES: [&'static str; 4] = ["ascii", "unicode", "half-block", "braille"];
+
+    // whether items are drawn as blocks and dots that only their colour
+    // tells apart
+    pub fn by_colour(&self) -> bool {
+        return matches!(self, RenderMode::HalfBlock | RenderMode::Braille);
+    }
+}
+
+impl FromStr for RenderMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ascii"      => Ok(RenderMode::Ascii),
+            "unicode"    => Ok(RenderMode::Unicode),
+            "half-block" => Ok(RenderMode::HalfBlock),
+            "braille"    => Ok(RenderMode::Braille),
+            _ => Err(format!("unknown render mode '{}', expected one of: {}", s, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+// Which sides of a cell the snake body connects to
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Links {
+    up: bool,
+    down: bool,
+    left: bool,
+    right: bool,
+}
+
+impl Links {
+    fn box_char(&self) -> char {
+        match (self.up, self.down, self.left, self.right) {
+            (true,  true,  false, false) => '│',
+            (false, false, true,  true ) => '─',
+            (true,  false, false, true ) => '└',
+            (true,  false, true,  false) => '┘',
+            (false, true,  false, true ) => '┌',
+            (false, true,  true,  false) => '┐',
+            (true,  false, false, false) => '╵',
+            (false, true,  false, false) => '╷',
+            (false, false, true,  false) => '╴',
+            (false, false, false, true ) => '╶',
+            _                            => '┼',
+        }
+    }
+}
+
+// Change how the snake is drawn while power-ups are acting on it
+pub fn effect_theme(theme: &Theme, effects: &[Effect]) -> Theme {
+    let mut modifier = Modifier::empty();
+    for effect in effects.iter() {
+        modifier |= match effect.kind {
+            PowerUp::Ghost      => Modifier::DIM,
+            PowerUp::SpeedBoost => Modifier::BOLD,
+            PowerUp::SlowMotion => Modifier::ITALIC,
+            _                   => Modifier::empty(),
+        };
+    }
+    let mut theme = theme.clone();
+    theme.snake.style = theme.snake.style.add_modifier(modifier);
+    theme.snake_head.style = theme.snake_head.style.add_modifier(modifier);
+    return theme;
+}
+
+// One line summary of the running power-ups for the HUD
+pub fn effect_summary(effects: &[Effect]) -> String {
+    return effects.iter().map(|e| match e.kind {
+        PowerUp::ScoreMultiplier => format!("x{}:{}", e.level, e.remaining),
+        kind                     => format!("{}:{}", kind, e.remaining),
+    }).collect::<Vec<String>>().join(" ");
+}
+
+// Render the board part of the frame, top row first.
+pub fn render_board(rd: &RenderData, theme: &Theme, mode: RenderMode) -> Vec<Spans<'static>> {
+    let rows: Vec<usize> = (0..board_rows(board_size(rd), rd.hex, mode)).collect();
+    return render_rows(rd, theme, mode, &rows);
+}
+
+// Render just these rows of the board, counted from the top, so a frame
+// can redraw the parts that have changed and leave the rest.
+pub fn render_rows(rd: &RenderData, theme: &Theme, mode: RenderMode, rows: &[usize]) -> Vec<Spans<'static>> {
+    let (_, height) = board_size(rd);
+    // hex cells only have the one layout
+    if rd.hex {
+        return rows.iter().map(|row| hex_row(rd, theme, height-1-row)).collect();
+    }
+    match mode {
+        RenderMode::Ascii     => rows.iter().map(|row| ascii_row(rd, theme, height-1-row)).collect(),
+        RenderMode::Unicode   => {
+            let ys: Vec<usize> = rows.iter().map(|row| height-1-row).collect();
+            let links = snake_links(rd, &ys);
+            ys.iter().map(|y| unicode_row(rd, theme, &links[*y], *y)).collect()
+        },
+        RenderMode::HalfBlock => rows.iter().map(|row| half_block_row(rd, theme, *row)).collect(),
+        RenderMode::Braille   => rows.iter().map(|row| braille_row(rd, theme, *row)).collect(),
+    }
+}
+
+// how many rows of characters the board takes up
+pub fn board_rows(size: (usize, usize), hex: bool, mode: RenderMode) -> usize {
+    let (_, height) = size;
+    if hex {
+        return height;
+    }
+    match mode {
+        RenderMode::Ascii | RenderMode::Unicode => height,
+        RenderMode::HalfBlock => height.div_ceil(2),
+        RenderMode::Braille   => height.div_ceil(4),
+    }
+}
+
+// The rows, counted from the top, that have to be drawn again when these
+// cells change. In unicode the snake's body links reach into the cells
+// above and below, so their rows go too.
+pub fn rows_touched(size: (usize, usize), hex: bool, mode: RenderMode, cells: &[[usize;2]]) -> Vec<usize> {
+    let (_, height) = size;
+    let mut rows = Vec::new();
+    for [_, y] in cells.iter() {
+        let from_top = height-1-y;
+        if hex {
+            rows.push(from_top);
+            continue;
+        }
+        match mode {
+            RenderMode::Ascii     => rows.push(from_top),
+            RenderMode::Unicode   => rows.extend_from_slice(&[(from_top+height-1)%height, from_top, (from_top+1)%height]),
+            RenderMode::HalfBlock => rows.push(from_top/2),
+            RenderMode::Braille   => rows.push(from_top/4),
+        }
+    }
+    rows.sort_unstable();
+    rows.dedup();
+    return rows;
+}
+
+// The board cell drawn at `col` and `row`, counted from the top left of the
+// board. Where a character holds several cells, the one at its top left is
+// as close as a click can get.
+pub fn cell_at(size: (usize, usize), hex: bool, mode: RenderMode, col: usize, row: usize) -> Option<[usize;2]> {
+    let (width, height) = size;
+    let (x, from_top) = if hex {
+        let from_top = row;
+        // each row is indented a character more than the one below
+        let indent = height.checked_sub(from_top+1)?;
+        (col.checked_sub(indent)?/2, from_top)
+    } else {
+        match mode {
+            RenderMode::Ascii | RenderMode::Unicode => (col/2, row),
+            RenderMode::HalfBlock => (col, 2*row),
+            RenderMode::Braille   => (2*col, 4*row),
+        }
+    };
+    if x >= width || from_top >= height {
+        return None;
+    }
+    return Some([x, height-1-from_top]);
+}
+
+fn board_size(rd: &RenderData) -> (usize, usize) {
+    let width  = rd.rm.len();
+    let height = rd.rm.first().map(|column| column.len()).unwrap_or(0);
+    return (width, height);
+}
+
+// The glyph for the item at x,y, with the empty cell the head is about to
+// move into drawn in the head's colour, so a turn shows before it happens
+fn cell_span(rd: &RenderData, theme: &Theme, x: usize, y: usize) -> Span<'static> {
+    let item = rd.rm[x][y];
+    let glyph = theme.glyph(item);
+    if let Item::Nothing = item {
+        if rd.planned == Some([x,y]) {
+            return Span::styled(glyph.symbol, theme.snake_head.style.add_modifier(Modifier::DIM));
+        }
+    }
+    return Span::styled(glyph.symbol, glyph.style);
+}
+
+fn ascii_row(rd: &RenderData, theme: &Theme, y: usize) -> Spans<'static> {
+    let (width, _) = board_size(rd);
+    let row_spans : Vec<Span> = (0..width).map(|x| cell_span(rd, theme, x, y)).collect();
+    return Spans::from(row_spans);
+}
+
+// Like ASCII, but each row starts half a cell (one character) further right
+// than the row below, so the cells sit in a honeycomb.
+fn hex_row(rd: &RenderData, theme: &Theme, y: usize) -> Spans<'static> {
+    let (width, _) = board_size(rd);
+    let mut row_spans = vec![Span::raw(" ".repeat(y))];
+    row_spans.extend((0..width).map(|x| cell_span(rd, theme, x, y)));
+    return Spans::from(row_spans);
+}
+
+// work out which way `to` lies from `from`, allowing for wrapping round the
+// edge of the board. Cells that are not neighbours give no link.
+fn link_between(from: [usize;2], to: [usize;2], width: usize, height: usize, links: &mut Links) {
+    let [fx,fy] = from;
+    let [tx,ty] = to;
+    if fx == tx {
+        if ty == (fy+1)%height {
+            links.up = true;
+        } else if fy == (ty+1)%height {
+            links.down = true;
+        }
+    } else if fy == ty {
+        if tx == (fx+1)%width {
+            links.right = true;
+        } else if fx == (tx+1)%width {
+            links.left = true;
+        }
+    }
+}
+
+// The links of each cell in the rows `ys`, indexed [y][x]. Other rows are
+// left empty.
+fn snake_links(rd: &RenderData, ys: &[usize]) -> Vec<Vec<Links>> {
+    let (width, height) = board_size(rd);
+    let mut links = vec![Vec::new(); height];
+    for y in ys.iter() {
+        links[*y] = vec![Links::default(); width];
+    }
+    for (index,seg) in rd.snake.iter().enumerate() {
+        let [x,y] = *seg;
+        if links[y].is_empty() {
+            continue;
+        }
+        if index > 0 {
+            link_between(*seg, rd.snake[index-1], width, height, &mut links[y][x]);
+        }
+        if let Some(next) = rd.snake.get(index+1) {
+            link_between(*seg, *next, width, height, &mut links[y][x]);
+        }
+    }
+    return links;
+}
+
+fn unicode_row(rd: &RenderData, theme: &Theme, links: &[Links], y: usize) -> Spans<'static> {
+    let (width, _) = board_size(rd);
+    let row_spans : Vec<Span> = (0..width).map(|x| {
+        match rd.rm[x][y] {
+            Item::Snake => {
+                let cell = links[x];
+                let pad = if cell.left { '─' } else { ' ' };
+                Span::styled(format!("{}{}", pad, cell.box_char()), theme.snake.style)
+            },
+            Item::SnakeHead => {
+                let pad = if links[x].left { '─' } else { ' ' };
+                Span::styled(format!("{}●", pad), theme.snake_head.style)
+            },
+            _ => cell_span(rd, theme, x, y),
+        }
+    }).collect();
+    return Spans::from(row_spans);
+}
+
+// colour used for an item when only colour can be shown
+fn item_colour(theme: &Theme, item: Item) -> Option<Color> {
+    if let Item::Nothing = item {
+        return None;
+    }
+    return Some(theme.glyph(item).style.fg.unwrap_or(Color::White));
+}
+
+// Items that should win when several share one character
+fn item_priority(item: Item) -> usize {
+    match item {
+        Item::Nothing                => 0,
+        Item::Wall                   => 1,
+        Item::Snake                  => 2,
+        Item::Portal                 => 3,
+        Item::Food(FoodGroup::Grow)   => 4,
+        Item::Food(FoodGroup::Shrink) => 5,
+        Item::Food(FoodGroup::PowerUp(_)) => 6,
+        Item::Critter(CritterKind::Mouse) => 7,
+        Item::Food(FoodGroup::Poison) => 8,
+        Item::Critter(_)             => 9,
+        Item::SnakeHead              => 10,
+    }
+}
+
+fn half_block_row(rd: &RenderData, theme: &Theme, row: usize) -> Spans<'static> {
+    let (width, height) = board_size(rd);
+    // top of the screen is the highest y
+    let top_y = height-1-2*row;
+    let row_spans : Vec<Span> = (0..width).map(|x| {
+        let top    = item_colour(theme, rd.rm[x][top_y]);
+        let bottom = if top_y > 0 { item_colour(theme, rd.rm[x][top_y-1]) } else { None };
+        match (top, bottom) {
+            (None, None)       => Span::raw(" "),
+            (Some(t), None)    => Span::styled("▀", Style::default().fg(t)),
+            (None, Some(b))    => Span::styled("▄", Style::default().fg(b)),
+            (Some(t), Some(b)) => Span::styled("▀", Style::default().fg(t).bg(b)),
+        }
+    }).collect();
+    return Spans::from(row_spans);
+}
+
+// bit for each dot in a braille character, indexed [column][row]
+const BRAILLE_DOTS: [[u32; 4]; 2] = [
+    [0x01, 0x02, 0x04, 0x40],
+    [0x08, 0x10, 0x20, 0x80],
+];
+
+fn braille_row(rd: &RenderData, theme: &Theme, row: usize) -> Spans<'static> {
+    let (width, height) = board_size(rd);
+    let cols = width.div_ceil(2);
+    let row_spans : Vec<Span> = (0..cols).map(|col| {
+        let mut bits = 0;
+        let mut strongest = Item::Nothing;
+        for (dx, dots) in BRAILLE_DOTS.iter().enumerate() {
+            for (dy, dot) in dots.iter().enumerate() {
+                let x = 2*col+dx;
+                let from_top = 4*row+dy;
+                if x >= width || from_top >= height {
+                    continue;
+                }
+                let item = rd.rm[x][height-1-from_top];
+                if let Item::Nothing = item {
+                    continue;
+                }
+                bits |= dot;
+                if item_priority(item) > item_priority(strongest) {
+                    strongest = item;
+                }
+            }
+        }
+        let c = std::char::from_u32(0x2800+bits).unwrap();
+        match item_colour(theme, strongest) {
+            Some(colour) => Span::styled(c.to_string(), Style::default().fg(colour)),
+            None         => Span::raw(c.to_string()),
+        }
+    }).collect();
+    return Spans::from(row_spans);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    // 3x3 board with a snake running [1,0] -> [1,1] -> [0,1], head first
+    fn test_data() -> RenderData {
+        let mut rm = vec![vec![Item::Nothing; 3]; 3];
+        rm[1][0] = Item::SnakeHead;
+        rm[1][1] = Item::Snake;
+        rm[0][1] = Item::Snake;
+        RenderData {
+            rm: Rc::new(rm),
+            changed: None,
+            snake: vec![[1,0],[1,1],[0,1]],
+            score: 0,
+            combo: 0,
+            speed: crate::game::SPEED_UNIT,
+            status: None,
+            effects: Vec::new(),
+            hex: false,
+            planned: None,
+        }
+    }
+
+    fn row_text(spans: &Spans) -> String {
+        spans.0.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn parse_render_modes() {
+        for name in RenderMode::NAMES.iter() {
+            assert!(RenderMode::from_str(name).is_ok());
+        }
+        assert!(RenderMode::from_str("vector").is_err());
+    }
+
+    #[test]
+    fn effects_change_snake_style() {
+        let theme = Theme::default();
+        let ghost = Effect { kind: PowerUp::Ghost, remaining: 3, level: 1 };
+        let multiplier = Effect { kind: PowerUp::ScoreMultiplier, remaining: 9, level: 2 };
+        let changed = effect_theme(&theme, &[ghost, multiplier]);
+        assert!(changed.snake.style.add_modifier.contains(Modifier::DIM));
+        assert!(changed.snake_head.style.add_modifier.contains(Modifier::DIM));
+        assert_eq!(changed.grow, theme.grow);
+        assert_eq!(effect_summary(&[ghost, multiplier]), "ghost:3 x2:9");
+    }
+
+    #[test]
+    fn box_chars() {
+        let links = Links { up: true, down: true, left: false, right: false };
+        assert_eq!(links.box_char(), '│');
+        let links = Links { up: false, down: true, left: false, right: true };
+        assert_eq!(links.box_char(), '┌');
+        let links = Links { up: false, down: false, left: true, right: false };
+        assert_eq!(links.box_char(), '╴');
+    }
+
+    #[test]
+    fn links_wrap_round_edges() {
+        let mut links = Links::default();
+        link_between([0,0], [4,0], 5, 5, &mut links);
+        assert_eq!(links, Links { left: true, ..Links::default() });
+        let mut links = Links::default();
+        link_between([0,4], [0,0], 5, 5, &mut links);
+        assert_eq!(links, Links { up: true, ..Links::default() });
+        let mut links = Links::default();
+        link_between([0,0], [2,2], 5, 5, &mut links);
+        assert_eq!(links, Links::default());
+    }
+
+    #[test]
+    fn unicode_connects_body() {
+        let rows = render_board(&test_data(), &Theme::default(), RenderMode::Unicode);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(row_text(&rows[0]), " - - -");
+        assert_eq!(row_text(&rows[1]), " ╶─┐ -");
+        assert_eq!(row_text(&rows[2]), " - ● -");
+    }
+
+    #[test]
+    fn hex_rows_are_offset() {
+        let mut rd = test_data();
+        rd.hex = true;
+        // the same for every mode
+        for mode in [RenderMode::Ascii, RenderMode::Braille].iter() {
+            let rows = render_board(&rd, &Theme::default(), *mode);
+            assert_eq!(rows.len(), 3);
+            assert_eq!(row_text(&rows[0]), "   - - -");
+            assert_eq!(row_text(&rows[1]), "  * * -");
+            assert_eq!(row_text(&rows[2]), " - O -");
+        }
+    }
+
+    #[test]
+    fn planned_cell_is_marked() {
+        let theme = Theme::default();
+        let mut rd = test_data();
+        rd.planned = Some([2,0]);
+        let rows = render_board(&rd, &theme, RenderMode::Ascii);
+        assert_eq!(row_text(&rows[2]), " - O -");
+        assert_eq!(rows[2].0[2].style, theme.snake_head.style.add_modifier(Modifier::DIM));
+        assert_eq!(rows[2].0[0].style, theme.nothing.style);
+        // only an empty cell is marked
+        rd.planned = Some([1,1]);
+        assert_eq!(render_board(&rd, &theme, RenderMode::Ascii)[1].0[1].style, theme.snake.style);
+    }
+
+    #[test]
+    fn half_block_packs_two_rows() {
+        let rows = render_board(&test_data(), &Theme::default(), RenderMode::HalfBlock);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(row_text(&rows[0]), "▄▄ ");
+        assert_eq!(row_text(&rows[1]), " ▀ ");
+        assert_eq!(rows[0].0[0].style.fg, Some(Color::LightGreen));
+        assert_eq!(rows[1].0[1].style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn braille_packs_block() {
+        let rows = render_board(&test_data(), &Theme::default(), RenderMode::Braille);
+        assert_eq!(rows.len(), 1);
+        // dots: second row left, second and third rows right
+        let expected = std::char::from_u32(0x2800 + 0x02 + 0x10 + 0x20).unwrap();
+        assert_eq!(row_text(&rows[0]), format!("{}\u{2800}", expected));
+        assert_eq!(rows[0].0[0].style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn redraw_rows_of_changed_cells() {
+        let size = (3, 9);
+        let cells = [[0,8], [2,3]];
+        assert_eq!(rows_touched(size, false, RenderMode::Ascii, &cells), vec![0, 5]);
+        // the rows either side too, wrapping round
+        assert_eq!(rows_touched(size, false, RenderMode::Unicode, &cells), vec![0, 1, 4, 5, 6, 8]);
+        assert_eq!(rows_touched(size, false, RenderMode::HalfBlock, &cells), vec![0, 2]);
+        assert_eq!(rows_touched(size, false, RenderMode::Braille, &cells), vec![0, 1]);
+        assert_eq!(rows_touched(size, true, RenderMode::Braille, &cells), vec![0, 5]);
+        assert_eq!(board_rows(size, false, RenderMode::Braille), 3);
+
+        // a row on its own comes out as it does in the whole board
+        let rd = test_data();
+        let theme = Theme::default();
+        let board = render_board(&rd, &theme, RenderMode::Unicode);
+        let rows = render_rows(&rd, &theme, RenderMode::Unicode, &[1]);
+        assert_eq!(rows, vec![board[1].clone()]);
+    }
+
+    #[test]
+    fn cells_under_clicks() {
+        let size = (3, 2);
+        // two characters a cell, top row first
+        assert_eq!(cell_at(size, false, RenderMode::Ascii, 0, 0), Some([0,1]));
+        assert_eq!(cell_at(size, false, RenderMode::Unicode, 5, 1), Some([2,0]));
+        assert_eq!(cell_at(size, false, RenderMode::Ascii, 6, 0), None);
+        assert_eq!(cell_at(size, false, RenderMode::Ascii, 0, 2), None);
+        assert_eq!(cell_at(size, false, RenderMode::HalfBlock, 1, 0), Some([1,1]));
+        assert_eq!(cell_at((5, 8), false, RenderMode::Braille, 1, 1), Some([2,3]));
+        // the top row of a hex board is indented by one
+        assert_eq!(cell_at(size, true, RenderMode::Ascii, 0, 0), None);
+        assert_eq!(cell_at(size, true, RenderMode::Ascii, 1, 0), Some([0,1]));
+        assert_eq!(cell_at(size, true, RenderMode::Ascii, 0, 1), Some([0,0]));
+    }
+}
@@ -0,0 +1,284 @@
+use std::str::FromStr;
+use tui::style::{Color, Modifier, Style};
+
+use crate::game::{Item, FoodGroup, PowerUp, CritterKind};
+
+// How a single board cell is drawn. Every item gets its own symbol as well as
+// a colour, so no information is carried by colour alone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    pub symbol: &'static str,
+    pub style: Style,
+}
+
+impl Glyph {
+    const fn new(symbol: &'static str, style: Style) -> Self {
+        Self { symbol, style }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub snake_head: Glyph,
+    pub snake: Glyph,
+    pub nothing: Glyph,
+    pub grow: Glyph,
+    pub shrink: Glyph,
+    pub poison: Glyph,
+    pub portal: Glyph,
+    pub mouse: Glyph,
+    pub bouncer: Glyph,
+    pub patrol: Glyph,
+    pub wall: Glyph,
+    // each power-up has its own symbol, but they share a colour
+    pub powerup: Style,
+}
+
+const fn fg(color: Color) -> Style {
+    Style {
+        fg: Some(color),
+        bg: None,
+        add_modifier: Modifier::empty(),
+        sub_modifier: Modifier::empty(),
+    }
+}
+
+const fn bold(color: Color) -> Style {
+    Style {
+        fg: Some(color),
+        bg: None,
+        add_modifier: Modifier::BOLD,
+        sub_modifier: Modifier::empty(),
+    }
+}
+
+const PLAIN: Style = Style {
+    fg: None,
+    bg: None,
+    add_modifier: Modifier::empty(),
+    sub_modifier: Modifier::empty(),
+};
+
+impl Theme {
+    pub const NAMES: [&'static str; 5] = ["default", "high-contrast", "deuteranopia", "protanopia", "monochrome"];
+
+    pub fn default_theme() -> Self {
+        Self {
+            name: "default",
+            snake_head: Glyph::new(" O", fg(Color::Green)),
+            snake:      Glyph::new(" *", fg(Color::LightGreen)),
+            nothing:    Glyph::new(" -", fg(Color::Gray)),
+            grow:       Glyph::new(" o", fg(Color::Blue)),
+            shrink:     Glyph::new(" v", fg(Color::Yellow)),
+            poison:     Glyph::new(" x", fg(Color::Red)),
+            portal:     Glyph::new(" %", fg(Color::Cyan)),
+            mouse:      Glyph::new(" &", fg(Color::White)),
+            bouncer:    Glyph::new(" B", fg(Color::LightRed)),
+            patrol:     Glyph::new(" H", fg(Color::Indexed(208))),
+            wall:       Glyph::new("##", fg(Color::DarkGray)),
+            powerup:    fg(Color::Magenta),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast",
+            snake_head: Glyph::new(" @", bold(Color::White)),
+            snake:      Glyph::new(" #", bold(Color::LightGreen)),
+            nothing:    Glyph::new(" .", fg(Color::DarkGray)),
+            grow:       Glyph::new(" +", bold(Color::LightCyan)),
+            shrink:     Glyph::new(" v", bold(Color::LightYellow)),
+            poison:     Glyph::new(" X", bold(Color::LightMagenta)),
+            portal:     Glyph::new(" %", bold(Color::Indexed(141))),
+            mouse:      Glyph::new(" &", bold(Color::Indexed(218))),
+            bouncer:    Glyph::new(" B", bold(Color::LightRed)),
+            patrol:     Glyph::new(" H", bold(Color::Indexed(208))),
+            wall:       Glyph::new("##", fg(Color::Gray)),
+            powerup:    bold(Color::LightBlue),
+        }
+    }
+
+    // Blue/orange palette, safe for red-green colour blindness (both
+    // deuteranopia and protanopia).
+    pub fn colourblind_safe(name: &'static str) -> Self {
+        Self {
+            name,
+            snake_head: Glyph::new(" O", bold(Color::LightBlue)),
+            snake:      Glyph::new(" *", fg(Color::Blue)),
+            nothing:    Glyph::new(" -", fg(Color::Gray)),
+            grow:       Glyph::new(" o", fg(Color::White)),
+            shrink:     Glyph::new(" v", fg(Color::Yellow)),
+            poison:     Glyph::new(" x", bold(Color::Indexed(208))),
+            portal:     Glyph::new(" %", fg(Color::LightMagenta)),
+            mouse:      Glyph::new(" &", fg(Color::LightCyan)),
+            bouncer:    Glyph::new(" B", bold(Color::Indexed(166))),
+            patrol:     Glyph::new(" H", bold(Color::Magenta)),
+            wall:       Glyph::new("##", fg(Color::DarkGray)),
+            powerup:    fg(Color::Cyan),
+        }
+    }
+
+    pub fn monochrome() -> Self {
+        Self {
+            name: "monochrome",
+            snake_head: Glyph::new(" O", Style { add_modifier: Modifier::BOLD, ..PLAIN }),
+            snake:      Glyph::new(" *", PLAIN),
+            nothing:    Glyph::new(" .", PLAIN),
+            grow:       Glyph::new(" o", PLAIN),
+            shrink:     Glyph::new(" v", PLAIN),
+            poison:     Glyph::new(" x", PLAIN),
+            portal:     Glyph::new(" %", PLAIN),
+            mouse:      Glyph::new(" &", PLAIN),
+            bouncer:    Glyph::new(" B", PLAIN),
+            patrol:     Glyph::new(" H", PLAIN),
+            wall:       Glyph::new("##", PLAIN),
+            powerup:    PLAIN,
+        }
+    }
+
+    // whether every item gets a colour of its own, for render modes that
+    // can't draw their symbols
+    pub fn is_coloured(&self) -> bool {
+        let styles = [
+            self.snake_head.style, self.snake.style, self.grow.style, self.shrink.style, self.poison.style,
+            self.portal.style, self.mouse.style, self.bouncer.style, self.patrol.style, self.wall.style,
+            self.powerup,
+        ];
+        // anything without a colour is drawn in white
+        let colours: Vec<Color> = styles.iter().map(|s| s.fg.unwrap_or(Color::White)).collect();
+        return colours.iter().enumerate().all(|(index, colour)| !colours[index+1..].contains(colour));
+    }
+
+    pub fn glyph(&self, item: Item) -> Glyph {
+        match item {
+            Item::Food(food_type) => self.food(food_type),
+            Item::SnakeHead => self.snake_head,
+            Item::Snake     => self.snake,
+            Item::Portal    => self.portal,
+            Item::Critter(CritterKind::Mouse)   => self.mouse,
+            Item::Critter(CritterKind::Bouncer) => self.bouncer,
+            Item::Critter(CritterKind::Patrol)  => self.patrol,
+            Item::Wall      => self.wall,
+            Item::Nothing   => self.nothing,
+        }
+    }
+
+    pub fn food(&self, food_type: FoodGroup) -> Glyph {
+        match food_type {
+            FoodGroup::Grow   => self.grow,
+            FoodGroup::Shrink => self.shrink,
+            FoodGroup::Poison => self.poison,
+            FoodGroup::PowerUp(kind) => Glyph::new(powerup_symbol(kind), self.powerup),
+        }
+    }
+}
+
+fn powerup_symbol(kind: PowerUp) -> &'static str {
+    match kind {
+        PowerUp::Ghost           => " G",
+        PowerUp::SpeedBoost      => " >",
+        PowerUp::SlowMotion      => " <",
+        PowerUp::ScoreMultiplier => " $",
+        PowerUp::Magnet          => " M",
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::default_theme()
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default"       => Ok(Self::default_theme()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            "deuteranopia"  => Ok(Self::colourblind_safe("deuteranopia")),
+            "protanopia"    => Ok(Self::colourblind_safe("protanopia")),
+            "monochrome"    => Ok(Self::monochrome()),
+            _ => Err(format!("unknown theme '{}', expected one of: {}", s, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn all_items() -> Vec<Item> {
+        let mut items = vec![
+            Item::SnakeHead,
+            Item::Snake,
+            Item::Nothing,
+            Item::Food(FoodGroup::Grow),
+            Item::Food(FoodGroup::Shrink),
+            Item::Food(FoodGroup::Poison),
+            Item::Portal,
+            Item::Wall,
+        ];
+        items.extend(CritterKind::ALL.iter().map(|k| Item::Critter(*k)));
+        items.extend(PowerUp::ALL.iter().map(|p| Item::Food(FoodGroup::PowerUp(*p))));
+        return items;
+    }
+
+    #[test]
+    fn parse_theme_names() {
+        for name in Theme::NAMES.iter() {
+            let theme = Theme::from_str(name).unwrap();
+            assert_eq!(theme.name, *name);
+        }
+        assert!(Theme::from_str("HIGH-CONTRAST").is_ok());
+        assert!(Theme::from_str("rainbow").is_err());
+    }
+
+    #[test]
+    fn glyph_symbols_are_distinct() {
+        // no theme may rely on colour alone to tell items apart
+        for name in Theme::NAMES.iter() {
+            let theme = Theme::from_str(name).unwrap();
+            let symbols : Vec<&str> = all_items().into_iter().map(|i| theme.glyph(i).symbol).collect();
+            for (index,symbol) in symbols.iter().enumerate() {
+                assert!(!symbols[index+1..].contains(symbol), "{} reuses {:?}", name, symbol);
+            }
+        }
+    }
+
+    #[test]
+    fn item_colours_are_distinct() {
+        // half-block and braille modes only have the colour to go on
+        for name in Theme::NAMES.iter().filter(|n| **n != "monochrome") {
+            let theme = Theme::from_str(name).unwrap();
+            let mut items = all_items();
+            items.retain(|i| !matches!(i, Item::Nothing | Item::Food(FoodGroup::PowerUp(_))));
+            items.push(Item::Food(FoodGroup::PowerUp(PowerUp::Ghost)));
+            let colours: Vec<Option<Color>> = items.iter().map(|i| theme.glyph(*i).style.fg).collect();
+            for (index, colour) in colours.iter().enumerate() {
+                assert!(colour.is_some(), "{} leaves {:?} uncoloured", name, items[index]);
+                let clash = colours[index+1..].iter().position(|c| c == colour).map(|i| items[index+1+i]);
+                assert_eq!(clash, None, "{} gives {:?} the colour of {:?}", name, items[index], clash);
+            }
+        }
+    }
+
+    #[test]
+    fn only_monochrome_lacks_colour() {
+        for name in Theme::NAMES.iter() {
+            let theme = Theme::from_str(name).unwrap();
+            assert_eq!(theme.is_coloured(), *name != "monochrome", "{}", name);
+        }
+    }
+
+    #[test]
+    fn colourblind_themes_avoid_red_and_green() {
+        for name in ["deuteranopia", "protanopia"].iter() {
+            let theme = Theme::from_str(name).unwrap();
+            for item in all_items() {
+                let colour = theme.glyph(item).style.fg;
+                assert!(!matches!(colour, Some(Color::Red) | Some(Color::Green) | Some(Color::LightRed) | Some(Color::LightGreen)));
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-pub fn args_to_dims<'a>(input: &'a str) -> Option<[usize;2]> {
+pub fn args_to_dims(input: &str) -> Option<[usize;2]> {
     let mut comma_index: usize = 0;
     let w: usize;
     let h: usize;
@@ -28,6 +29,14 @@ pub fn args_to_dims<'a>(input: &'a str) -> Option<[usize;2]> {
     return Some([w,h]);
 }
 
+// one `period` on from `due`, or from `now` if that's already gone by
+pub fn reschedule(due: Instant, period: Duration, now: Instant) -> Instant {
+    if due+period > now {
+        return due+period;
+    }
+    return now+period;
+}
+
 
 #[cfg(test)]
 mod test {
@@ -47,4 +56,15 @@ mod test {
         assert_eq!(args_to_dims("6,b"),None);
         assert_eq!(args_to_dims("a,b"),None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn reschedule_keeps_time() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(500);
+        // running a little late doesn't push the next one back
+        assert_eq!(reschedule(start, tick, start+Duration::from_millis(100)), start+tick);
+        // but running a whole tick late starts again from now
+        let late = start+Duration::from_secs(3);
+        assert_eq!(reschedule(start, tick, late), late+tick);
+    }
+}